@@ -1,10 +1,33 @@
+// `verify` and `verify_preimage` build without std, for zkVM guests
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 mod utils;
 
-use anyhow::Result;
-pub use utils::tornado::Proof;
-use utils::tornado::{SparseMerkleTree, Tornado, TornadoMerkleTree};
-use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use alloc::vec::Vec;
+pub use utils::encode::{decode_input, encode_input};
+#[cfg(feature = "std")]
+pub use utils::tornado::strip_hex_prefix;
+#[cfg(feature = "std")]
+pub use utils::tornado::{
+    check_delay_anchor, BlockEntry, BlockList, BlockListFormat, CacheReport, EntryKind,
+    EventLogSource, FsSource, MemorySource, Note, NoteReport, Pool, ProveOutput, Provenance,
+    Resolution, Tornado,
+};
+use utils::tornado::{pedersen_hash, to_hash, TornadoMerkleTree, NULLIFIER_LEN, PREIMAGE_LEN};
+pub use utils::tornado::{
+    to_hash_str, try_to_hash, AnonymitySet, AsOf, DelayAnchor, EventLog, EventLogType, Journal,
+    Manifest, MinDelay, NamedInnocence, Proof, ProofMode, SignedManifest, Snapshot,
+    SparseMerkleTree,
+};
+#[cfg(feature = "wasm")]
+use {
+    utils::tornado::TornadoUtil,
+    wasm_bindgen::{prelude::wasm_bindgen, JsValue},
+};
 
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub async fn prove(note_list: Vec<JsValue>, block_list: Vec<JsValue>) -> Result<String, JsValue> {
     let note_list = note_list
@@ -14,7 +37,7 @@ pub async fn prove(note_list: Vec<JsValue>, block_list: Vec<JsValue>) -> Result<
                 "Parse note error, make sure you enter a string",
             ))
         })
-        .collect::<Result<_, _>>()?;
+        .collect::<Result<_, JsValue>>()?;
     let block_list = block_list
         .iter()
         .map(|block| {
@@ -22,9 +45,8 @@ pub async fn prove(note_list: Vec<JsValue>, block_list: Vec<JsValue>) -> Result<
                 "Parse block error, make sure you enter a string",
             ))
         })
-        .collect::<Result<_, _>>()?;
-    let tornado = Tornado::new(note_list, block_list)
-        .await
+        .collect::<Result<_, JsValue>>()?;
+    let tornado = Tornado::new(note_list, block_list, TornadoUtil::new())
         .map_err(|err| JsValue::from_str(&err.to_string()))?;
     let proof = tornado
         .prove()
@@ -45,32 +67,92 @@ pub fn verify(proof_list: Vec<Proof>) -> bool {
     let mut res = true;
 
     for proof in proof_list {
-        res =
-            res && TornadoMerkleTree::verify(
+        // at least one blocklist or allowlist statement
+        let statement = proof.innocence_tree_root.is_some()
+            || proof.association_tree_root.is_some()
+            || !proof.named_innocence.is_empty();
+        // the deposit is in the tree as of the snapshot, which must come early enough before the
+        // linked withdrawal, both blocks are public and checked on-chain by `check_delay_anchor`
+        let delay = match proof.min_delay {
+            Some(min_delay) => {
+                DelayAnchor::new(&proof).is_some_and(|anchor| min_delay.check(&anchor))
+            }
+            None => true,
+        };
+        let innocence = match (proof.innocence_tree_root, proof.innocence_proof) {
+            (Some(root), Some(innocence_proof)) => {
+                SparseMerkleTree::verify(root, proof.commitment, innocence_proof)
+            }
+            (None, None) => true,
+            _ => false,
+        };
+        let named_innocence = proof
+            .named_innocence
+            .into_iter()
+            .all(|named| SparseMerkleTree::verify(named.root, proof.commitment, named.proof));
+        // the issuer must have signed the blocklist root
+        let manifest = match &proof.manifest {
+            Some(signed) => {
+                proof.innocence_tree_root == Some(signed.manifest.root) && signed.verify()
+            }
+            None => true,
+        };
+        let association = match proof.association_tree_root {
+            Some(root) => TornadoMerkleTree::verify(
+                root,
+                proof.commitment,
+                proof.association_proof_element,
+                proof.association_proof_index,
+            ),
+            None => true,
+        };
+        // a linked withdrawal must spend the nullifier of the proof
+        let withdrawal = match &proof.withdrawal {
+            Some(log) => {
+                try_to_hash(log.nullifier_hash.trim_start_matches("0x"))
+                    == Some(proof.nullifier_hash)
+            }
+            None => true,
+        };
+
+        res = res
+            && statement
+            && TornadoMerkleTree::verify(
                 proof.accuracy_tree_root,
                 proof.commitment,
                 proof.accuracy_proof_element,
                 proof.accuracy_proof_index,
-            ) && SparseMerkleTree::verify(
-                proof.innocence_tree_root,
-                proof.commitment,
-                proof.innocence_proof,
-            );
+            )
+            && innocence
+            && named_innocence
+            && manifest
+            && association
+            && withdrawal
+            && delay;
     }
 
     res
 }
 
-#[cfg(test)]
+/// Check that the commitment and nullifier hash of the proof are derived from the note preimage,
+/// i.e. the prover owns the deposit.
+pub fn verify_preimage(proof: &Proof, preimage: &[u8]) -> bool {
+    let hash = |data: &[u8]| pedersen_hash(data).map(|hash| to_hash(&hash));
+
+    preimage.len() == PREIMAGE_LEN
+        && matches!(hash(preimage), Ok(commitment) if commitment == proof.commitment)
+        && matches!(
+            hash(&preimage[..NULLIFIER_LEN]),
+            Ok(nullifier_hash) if nullifier_hash == proof.nullifier_hash
+        )
+}
+
+#[cfg(all(test, feature = "wasm"))]
 mod tests {
     use super::*;
+    use utils::tornado::fixture::{COMMITMENT_HASH, COST_NOTE, NOTE, NULLIFIER_HASH, OTHER_HASH};
     use wasm_bindgen_test::*;
 
-    const NOTE: &str = r"tornado-eth-0.1-5-0xebcf5edb762e52e6eb0f33818c647cdceb75d1cd6609847ec56b750445de0b659a11796781c60aaf3ba5d693b360a77d5cff360c982ed9dc2fd419b858d3";
-    const COMMITMENT_HASH: &str =
-        "296137799075f986ce4c0bbbfdade2b96689d7720d2e8b59a84bf48f4afe9ad8";
-    const OTHER_HASH: &str = "296137799075f986ce4c0bbbfdade2b96689d7720d2e8b59a84bf48f4afe9ad9";
-
     #[wasm_bindgen_test]
     async fn test_success_circuit() {
         assert!(verify(
@@ -86,6 +168,89 @@ mod tests {
         ));
     }
 
+    #[wasm_bindgen_test]
+    async fn test_journal() {
+        let proof: Vec<Proof> = serde_json::from_str(
+            &prove(
+                vec![JsValue::from_str(NOTE)],
+                vec![JsValue::from_str(OTHER_HASH)],
+            )
+            .await
+            .unwrap(),
+        )
+        .unwrap();
+        let journal = Journal::new(&proof).unwrap();
+
+        assert_eq!(journal.nullifier_hash, vec![to_hash(NULLIFIER_HASH)]);
+        assert_eq!(
+            journal.accuracy_tree_root,
+            vec![proof[0].accuracy_tree_root]
+        );
+        assert_eq!(journal.innocence_tree_root, proof[0].innocence_tree_root);
+        assert!(Journal::new(&[]).is_none());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_verify_preimage() {
+        let proof: Vec<Proof> = serde_json::from_str(
+            &prove(
+                vec![JsValue::from_str(NOTE)],
+                vec![JsValue::from_str(OTHER_HASH)],
+            )
+            .await
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert!(verify_preimage(&proof[0], &Note::preimage(NOTE).unwrap()));
+        assert!(!verify_preimage(
+            &proof[0],
+            &Note::preimage(COST_NOTE).unwrap()
+        ));
+        assert!(!verify_preimage(&proof[0], &[]));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_association_circuit() {
+        let proof = Tornado::new(
+            vec![NOTE.into()],
+            vec![OTHER_HASH.into()],
+            TornadoUtil::new(),
+        )
+        .unwrap()
+        .set_allow_list(vec![OTHER_HASH.into(), COMMITMENT_HASH.into()])
+        .set_mode(ProofMode::Both)
+        .prove()
+        .await
+        .unwrap();
+        assert_eq!(proof[0].mode(), Some(ProofMode::Both));
+        assert!(verify(proof));
+
+        assert!(Tornado::new(vec![NOTE.into()], vec![], TornadoUtil::new())
+            .unwrap()
+            .set_allow_list(vec![OTHER_HASH.into()])
+            .set_mode(ProofMode::Inclusion)
+            .prove()
+            .await
+            .is_err());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_shared_pool() {
+        let proof = Tornado::new(
+            vec![NOTE.into(), NOTE.into()],
+            vec![OTHER_HASH.into()],
+            TornadoUtil::new(),
+        )
+        .unwrap()
+        .prove()
+        .await
+        .unwrap();
+
+        assert_eq!(proof.len(), 2);
+        assert_eq!(proof[0], proof[1]);
+    }
+
     #[wasm_bindgen_test]
     async fn test_fail_circuit() {
         assert!(prove(
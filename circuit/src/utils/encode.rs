@@ -0,0 +1,421 @@
+//! Encode proofs as the u64 input stream of zkwasm, read by `wasm_input`.
+//!
+//! The public input is the `Journal`, the private input is the rest of every proof in order, the
+//! `pool` and `anonymity_set` of a proof are left out, and so are the snapshot and withdrawal
+//! except the blocks and timestamps of the `delay_anchor` list:
+//!
+//! - public: `accuracy_tree_root`, `innocence_tree_root`, `association_tree_root`,
+//!   `nullifier_hash`, `min_delay`, `delay_anchor`, `named_innocence_root`, then an option of
+//!   `issuer_key` with the `manifest` fields in order
+//! - private: the manifest signature bytes if there is a manifest, then for each proof: the note
+//!   preimage bytes, which tie the public nullifier hash to the commitment, `commitment`,
+//!   `accuracy_proof_element`, `accuracy_proof_index`,
+//!   `innocence_proof`, `association_proof_element`, `association_proof_index`, then the proof of
+//!   every named blocklist
+//!
+//! A hash is 4 big-endian words, a bool is 0 or 1, a list is its length followed by the items,
+//! an option is 0, or 1 followed by the value, and bytes are the byte length followed by
+//! big-endian words with the last one zero padded, a name is its utf-8 bytes. A minimum delay is 0
+//! for blocks or 1 for seconds, followed by the amount, a delay anchor is its fields in order.
+use super::tornado::{
+    to_hash_str, AnonymitySet, DelayAnchor, Hash, Journal, Manifest, MinDelay, NamedInnocence,
+    Proof, SignedManifest, Snapshot, WithdrawLog,
+};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Returns the (private, public) input, `None` if `Journal::new` fails or there is not one note
+/// preimage for every proof.
+pub fn encode_input(
+    proof_list: &[Proof],
+    preimage_list: &[Vec<u8>],
+) -> Option<(Vec<u64>, Vec<u64>)> {
+    let journal = Journal::new(proof_list)?;
+    if proof_list.len() != preimage_list.len() {
+        return None;
+    }
+
+    let mut public = Encoder::default();
+    public.hash_list(&journal.accuracy_tree_root);
+    public.option(journal.innocence_tree_root.as_ref(), Encoder::hash);
+    public.option(journal.association_tree_root.as_ref(), Encoder::hash);
+    public.hash_list(&journal.nullifier_hash);
+    public.option(journal.min_delay.as_ref(), Encoder::min_delay);
+    public.len(journal.delay_anchor.len());
+    for anchor in &journal.delay_anchor {
+        public.delay_anchor(anchor);
+    }
+    public.len(journal.named_innocence_root.len());
+    for (name, root) in &journal.named_innocence_root {
+        public.bytes(name.as_bytes());
+        public.hash(root);
+    }
+    let manifest = journal.issuer_key.as_ref().zip(journal.manifest.as_ref());
+    public.option(manifest.as_ref(), |encoder, (issuer_key, manifest)| {
+        encoder.hash(issuer_key);
+        encoder.manifest(manifest);
+    });
+
+    let mut private = Encoder::default();
+    // the manifest is the same for every proof
+    if let Some(signed) = proof_list[0].manifest.as_ref() {
+        private.bytes(&signed.signature);
+    }
+    for (proof, preimage) in proof_list.iter().zip(preimage_list) {
+        private.bytes(preimage);
+        private.hash(&proof.commitment);
+        private.hash_list(&proof.accuracy_proof_element);
+        private.bool_list(&proof.accuracy_proof_index);
+        private.option(proof.innocence_proof.as_ref(), |encoder, proof| {
+            encoder.bytes(proof)
+        });
+        private.hash_list(&proof.association_proof_element);
+        private.bool_list(&proof.association_proof_index);
+        for named in &proof.named_innocence {
+            private.bytes(&named.proof);
+        }
+    }
+
+    Some((private.0, public.0))
+}
+
+/// Rebuild the proofs with the roots of the public input and their note preimages, `None` if the
+/// input runs out.
+pub fn decode_input(
+    private: impl Iterator<Item = u64>,
+    public: impl Iterator<Item = u64>,
+) -> Option<(Vec<Proof>, Vec<Vec<u8>>)> {
+    let mut public = Decoder(public);
+    let accuracy_tree_root = public.hash_list()?;
+    let innocence_tree_root = public.option(Decoder::hash)?;
+    let association_tree_root = public.option(Decoder::hash)?;
+    let nullifier_hash = public.hash_list()?;
+    let min_delay = public.option(Decoder::min_delay)?;
+    let mut delay_anchor = (0..public.len()?)
+        .map(|_| public.delay_anchor())
+        .collect::<Option<Vec<_>>>()?
+        .into_iter();
+    let named_innocence_root = (0..public.len()?)
+        .map(|_| Some((String::from_utf8(public.bytes()?).ok()?, public.hash()?)))
+        .collect::<Option<Vec<_>>>()?;
+    let manifest = public.option(|decoder| Some((decoder.hash()?, decoder.manifest()?)))?;
+
+    let mut private = Decoder(private);
+    let manifest = match manifest {
+        Some((public_key, manifest)) => Some(SignedManifest {
+            manifest,
+            public_key,
+            signature: private.bytes()?,
+        }),
+        None => None,
+    };
+    accuracy_tree_root
+        .into_iter()
+        .zip(nullifier_hash)
+        .map(|(accuracy_tree_root, nullifier_hash)| {
+            let preimage = private.bytes()?;
+            let commitment = private.hash()?;
+            let accuracy_proof_element = private.hash_list()?;
+            let accuracy_proof_index = private.bool_list()?;
+            let innocence_proof = private.option(Decoder::bytes)?;
+            let association_proof_element = private.hash_list()?;
+            let association_proof_index = private.bool_list()?;
+            // the snapshot and withdrawal only carry what `verify` checks the claim with
+            let (snapshot, withdrawal) = match min_delay {
+                Some(_) => {
+                    let anchor = delay_anchor.next()?;
+                    (
+                        Snapshot {
+                            block_number: anchor.snapshot_block,
+                            timestamp: anchor.snapshot_timestamp,
+                            ..Default::default()
+                        },
+                        Some(WithdrawLog {
+                            block_number: anchor.withdrawal_block,
+                            nullifier_hash: format!("0x{}", to_hash_str(&nullifier_hash)),
+                            timestamp: anchor
+                                .withdrawal_timestamp
+                                .map(|timestamp| timestamp.to_string()),
+                            ..Default::default()
+                        }),
+                    )
+                }
+                None => (Snapshot::default(), None),
+            };
+            let named_innocence = named_innocence_root
+                .iter()
+                .map(|(name, root)| {
+                    Some(NamedInnocence {
+                        name: name.clone(),
+                        root: *root,
+                        proof: private.bytes()?,
+                    })
+                })
+                .collect::<Option<_>>()?;
+
+            let proof = Proof {
+                commitment,
+                nullifier_hash,
+                accuracy_tree_root,
+                snapshot,
+                pool: String::new(),
+                innocence_tree_root,
+                association_tree_root,
+                accuracy_proof_element,
+                accuracy_proof_index,
+                innocence_proof,
+                association_proof_element,
+                association_proof_index,
+                withdrawal,
+                min_delay,
+                anonymity_set: AnonymitySet::default(),
+                named_innocence,
+                manifest: manifest.clone(),
+            };
+            Some((proof, preimage))
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|list| list.into_iter().unzip())
+}
+
+#[derive(Default)]
+struct Encoder(Vec<u64>);
+
+impl Encoder {
+    fn word(&mut self, word: u64) {
+        self.0.push(word);
+    }
+
+    fn len(&mut self, len: usize) {
+        self.0.push(len as u64);
+    }
+
+    fn hash(&mut self, hash: &Hash) {
+        self.0.extend(
+            hash.chunks(8)
+                .map(|word| u64::from_be_bytes(word.try_into().unwrap())),
+        );
+    }
+
+    fn hash_list(&mut self, list: &[Hash]) {
+        self.len(list.len());
+        list.iter().for_each(|hash| self.hash(hash));
+    }
+
+    fn bool_list(&mut self, list: &[bool]) {
+        self.len(list.len());
+        self.0.extend(list.iter().map(|b| *b as u64));
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.len(bytes.len());
+        self.0.extend(bytes.chunks(8).map(|word| {
+            let mut buf = [0; 8];
+            buf[..word.len()].copy_from_slice(word);
+            u64::from_be_bytes(buf)
+        }));
+    }
+
+    fn option<T>(&mut self, value: Option<&T>, f: impl FnOnce(&mut Self, &T)) {
+        match value {
+            Some(value) => {
+                self.0.push(1);
+                f(self, value);
+            }
+            None => self.0.push(0),
+        }
+    }
+
+    fn min_delay(&mut self, min_delay: &MinDelay) {
+        match *min_delay {
+            MinDelay::Block(n) => self.0.extend([0, n as u64]),
+            MinDelay::Second(n) => self.0.extend([1, n]),
+        }
+    }
+
+    fn delay_anchor(&mut self, anchor: &DelayAnchor) {
+        self.word(anchor.snapshot_block as u64);
+        self.option(anchor.snapshot_timestamp.as_ref(), |encoder, timestamp| {
+            encoder.word(*timestamp)
+        });
+        self.word(anchor.withdrawal_block as u64);
+        self.option(
+            anchor.withdrawal_timestamp.as_ref(),
+            |encoder, timestamp| encoder.word(*timestamp),
+        );
+    }
+
+    fn manifest(&mut self, manifest: &Manifest) {
+        self.bytes(manifest.issuer.as_bytes());
+        self.0
+            .extend([manifest.version, manifest.timestamp, manifest.entry_count]);
+        self.hash(&manifest.root);
+    }
+}
+
+struct Decoder<I: Iterator<Item = u64>>(I);
+
+impl<I: Iterator<Item = u64>> Decoder<I> {
+    fn word(&mut self) -> Option<u64> {
+        self.0.next()
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        self.0.next().map(|len| len as usize)
+    }
+
+    fn hash(&mut self) -> Option<Hash> {
+        let mut hash = [0; 32];
+        for word in hash.chunks_mut(8) {
+            word.copy_from_slice(&self.0.next()?.to_be_bytes());
+        }
+        Some(hash)
+    }
+
+    fn hash_list(&mut self) -> Option<Vec<Hash>> {
+        (0..self.len()?).map(|_| self.hash()).collect()
+    }
+
+    fn bool_list(&mut self) -> Option<Vec<bool>> {
+        (0..self.len()?)
+            .map(|_| self.0.next().map(|b| b == 1))
+            .collect()
+    }
+
+    fn bytes(&mut self) -> Option<Vec<u8>> {
+        let len = self.len()?;
+        let mut bytes = Vec::new();
+        while bytes.len() < len {
+            bytes.extend(self.0.next()?.to_be_bytes());
+        }
+        bytes.truncate(len);
+        Some(bytes)
+    }
+
+    fn option<T>(&mut self, f: impl FnOnce(&mut Self) -> Option<T>) -> Option<Option<T>> {
+        match self.0.next()? {
+            0 => Some(None),
+            _ => f(self).map(Some),
+        }
+    }
+
+    fn min_delay(&mut self) -> Option<MinDelay> {
+        match (self.0.next()?, self.0.next()?) {
+            (0, n) => n.try_into().ok().map(MinDelay::Block),
+            (1, n) => Some(MinDelay::Second(n)),
+            _ => None,
+        }
+    }
+
+    fn delay_anchor(&mut self) -> Option<DelayAnchor> {
+        Some(DelayAnchor {
+            snapshot_block: self.word()?.try_into().ok()?,
+            snapshot_timestamp: self.option(Self::word)?,
+            withdrawal_block: self.word()?.try_into().ok()?,
+            withdrawal_timestamp: self.option(Self::word)?,
+        })
+    }
+
+    fn manifest(&mut self) -> Option<Manifest> {
+        Some(Manifest {
+            issuer: String::from_utf8(self.bytes()?).ok()?,
+            version: self.0.next()?,
+            timestamp: self.0.next()?,
+            entry_count: self.0.next()?,
+            root: self.hash()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_encode() {
+        let proof = Proof {
+            commitment: [1; 32],
+            nullifier_hash: [2; 32],
+            accuracy_tree_root: [3; 32],
+            snapshot: Snapshot::default(),
+            pool: String::new(),
+            innocence_tree_root: Some([4; 32]),
+            association_tree_root: None,
+            accuracy_proof_element: vec![[5; 32], [6; 32]],
+            accuracy_proof_index: vec![true, false],
+            innocence_proof: Some(vec![7; 13]),
+            association_proof_element: vec![],
+            association_proof_index: vec![],
+            withdrawal: None,
+            min_delay: None,
+            anonymity_set: AnonymitySet::default(),
+            named_innocence: vec![NamedInnocence {
+                name: "ofac".into(),
+                root: [8; 32],
+                proof: vec![9; 40],
+            }],
+            manifest: None,
+        };
+        let proof_list = vec![proof.clone(), proof.clone()];
+        let preimage_list = vec![vec![10; 62], vec![11; 62]];
+
+        let (private, public) = encode_input(&proof_list, &preimage_list).unwrap();
+        assert_eq!(
+            decode_input(private.clone().into_iter(), public.clone().into_iter()),
+            Some((proof_list.clone(), preimage_list.clone()))
+        );
+        assert!(encode_input(&proof_list, &preimage_list[1..]).is_none());
+        assert_eq!(
+            decode_input(private[1..].iter().copied(), public.into_iter()),
+            None
+        );
+
+        let proof_list = vec![Proof {
+            snapshot: Snapshot {
+                block_number: 10,
+                timestamp: Some(1000),
+                ..Default::default()
+            },
+            withdrawal: Some(WithdrawLog {
+                block_number: 20,
+                nullifier_hash: format!("0x{}", to_hash_str(&[2; 32])),
+                timestamp: Some("2000".into()),
+                ..Default::default()
+            }),
+            min_delay: Some(MinDelay::Second(1000)),
+            ..proof.clone()
+        }];
+        let (private, public) = encode_input(&proof_list, &preimage_list[..1]).unwrap();
+        assert_eq!(
+            decode_input(private.into_iter(), public.into_iter()),
+            Some((proof_list.clone(), preimage_list[..1].to_vec()))
+        );
+        assert!(encode_input(
+            &[Proof {
+                withdrawal: None,
+                ..proof_list[0].clone()
+            }],
+            &preimage_list[..1]
+        )
+        .is_none());
+
+        let manifest = Manifest {
+            issuer: "ofac".into(),
+            version: 1,
+            timestamp: 1676880000,
+            entry_count: 1,
+            root: [4; 32],
+        };
+        let proof_list = vec![Proof {
+            manifest: Some(manifest.sign(&[7; 32])),
+            ..proof
+        }];
+        let (private, public) = encode_input(&proof_list, &preimage_list[..1]).unwrap();
+        assert_eq!(
+            decode_input(private.into_iter(), public.into_iter()),
+            Some((proof_list, preimage_list[..1].to_vec()))
+        );
+    }
+}
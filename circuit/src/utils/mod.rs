@@ -1 +1,2 @@
+pub mod encode;
 pub mod tornado;
@@ -1,108 +1,532 @@
+#[cfg(feature = "std")]
+mod blocklist;
+#[cfg(feature = "std")]
+mod cache;
+#[cfg(all(test, feature = "std"))]
+pub(crate) mod fixture;
+#[cfg(feature = "wasm")]
+mod js;
+mod manifest;
 mod merkle;
+mod mimc;
+#[cfg(feature = "std")]
 mod note;
+mod pedersen;
+#[cfg(feature = "std")]
+mod report;
+#[cfg(feature = "std")]
+mod resolve;
+#[cfg(feature = "std")]
+mod source;
+#[cfg(feature = "std")]
+mod store;
 mod typ;
 
-use anyhow::{anyhow, Result};
-use futures::stream::FuturesUnordered;
-use futures::StreamExt;
+#[cfg(feature = "std")]
+pub use blocklist::*;
+#[cfg(feature = "std")]
+pub use cache::*;
+#[cfg(feature = "wasm")]
+pub use js::TornadoUtil;
+pub use manifest::*;
 pub use merkle::*;
-use note::Note;
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::rc::Rc;
+#[cfg(feature = "std")]
+pub use note::{Note, Pool};
+pub use pedersen::pedersen_hash;
+#[cfg(feature = "std")]
+pub use report::NoteReport;
+#[cfg(feature = "std")]
+pub use resolve::*;
+#[cfg(feature = "std")]
+pub use source::*;
 pub use typ::*;
+#[cfg(feature = "std")]
+use {
+    anyhow::{anyhow, bail, Result},
+    futures::stream::FuturesUnordered,
+    futures::StreamExt,
+    std::collections::{HashMap, HashSet},
+    std::fs,
+    std::path::PathBuf,
+};
 
-pub struct Tornado {
+/// The proofs of `Tornado::prove_with_report` and what it found on the way.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct ProveOutput {
+    // in the order of the note list
+    pub proof: Vec<Proof>,
+    // how every blocklist entry resolves through the deposits in the trees of the proofs
+    pub resolution: Vec<Resolution>,
+    // the check of the deposit cache of every pool, sorted by pool
+    pub cache_report: Vec<(Pool, CacheReport)>,
+}
+
+// the deposit tree of a pool as of its snapshot, built once and shared by the notes of the pool
+#[cfg(feature = "std")]
+struct PoolTree {
+    tree: TornadoMerkleTree,
+    snapshot: Snapshot,
+    // the deposits in the cache, the first `snapshot.leaf_count` of them are in the tree
+    deposit_list: Vec<DepositLog>,
+    report: CacheReport,
+}
+
+#[cfg(feature = "std")]
+impl PoolTree {
+    fn in_tree(&self) -> &[DepositLog] {
+        &self.deposit_list[..self.snapshot.leaf_count]
+    }
+}
+
+#[cfg(feature = "std")]
+pub struct Tornado<S: EventLogSource> {
     note_list: Vec<Note>,
     block_list: Vec<HashStr>,
-    util: TornadoUtil,
+    allow_list: Vec<HashStr>,
+    mode: ProofMode,
+    source: S,
+    // where the deposit trees are kept between runs
+    tree_dir: Option<PathBuf>,
+    as_of: Option<AsOf>,
+    // refuse to prove on a damaged deposit cache
+    strict: bool,
+    // roots the deposit tree should match, a root without pool applies to every pool
+    known_root: Vec<(Option<Pool>, Hash)>,
+    // link every proof to the withdrawal of its note
+    withdrawal: bool,
+    min_delay: Option<MinDelay>,
+    // blocklists of verifiers, each gets its own exclusion proof
+    named_block_list: Vec<(String, Vec<HashStr>)>,
+    // issuer signature of the blocklist
+    manifest: Option<SignedManifest>,
+    // stored tree of the blocklist, proven against instead of a list
+    block_list_tree: Option<SparseMerkleTree>,
 }
 
-impl Default for Tornado {
-    fn default() -> Self {
+#[cfg(feature = "std")]
+impl<S: EventLogSource> Tornado<S> {
+    pub fn new(note_list: Vec<String>, block_list: Vec<String>, source: S) -> Result<Self> {
         Self {
             note_list: vec![],
             block_list: vec![],
-            util: TornadoUtil::new(),
+            allow_list: vec![],
+            mode: ProofMode::default(),
+            source,
+            tree_dir: None,
+            as_of: None,
+            strict: false,
+            known_root: vec![],
+            withdrawal: false,
+            min_delay: None,
+            named_block_list: vec![],
+            manifest: None,
+            block_list_tree: None,
         }
+        .set_block_list(block_list)
+        .parse_note(note_list)
     }
-}
 
-impl Tornado {
-    pub async fn new(note_list: Vec<String>, block_list: Vec<String>) -> Result<Self> {
-        let s = Self::default().set_block_list(block_list);
-        s.util.init().await;
-        s.parse_note(note_list)
+    // proofs keep the order of the note list, zkVM hosts pair them with note preimages
+    pub async fn prove(self) -> Result<Vec<Proof>> {
+        Ok(self.prove_with_report().await?.proof)
     }
 
-    pub async fn prove(self) -> Result<Vec<Proof>> {
-        let util = &self.util;
-        let accuracy_tree_cache = Rc::new(RefCell::new(HashMap::new()));
-        let innocence_tree = &SparseMerkleTree::new(self.block_list.clone());
-        let task_list = FuturesUnordered::new();
-
-        for note in &self.note_list {
-            let accuracy_tree_cache = Rc::clone(&accuracy_tree_cache);
-            task_list.push(async move {
-                let mut accuracy_tree_cache = accuracy_tree_cache.borrow_mut();
-                let accuracy_tree = match accuracy_tree_cache.get(note) {
-                    Some(tree) => tree,
-                    None => {
-                        let log_list = note
-                            .read_event_log(Some(EventLogType::Deposit), util)
-                            .await?;
-                        let leaves = log_list
-                            .into_iter()
-                            .map(|log| match log {
-                                EventLog::Deposit(log) => {
-                                    log.commitment.trim_start_matches("0x").into()
-                                }
-                                _ => unreachable!(),
-                            })
-                            .collect::<Vec<String>>();
-
-                        let tree = TornadoMerkleTree::new(leaves);
-                        accuracy_tree_cache.insert(note.clone(), tree);
-                        accuracy_tree_cache.get(note).unwrap()
-                    }
-                };
+    /// Also tell how the blocklist entries resolve to commitments through the deposits in the
+    /// trees of the proofs, and what the check of the deposit cache of every pool found, a
+    /// damaged one only fails with `strict`.
+    pub async fn prove_with_report(mut self) -> Result<ProveOutput> {
+        let withdraw_log_cache = self.withdraw_log_cache().await?;
+        let pool_tree = self.pool_tree(&withdraw_log_cache).await?;
+        let deposit_list = || pool_tree.values().flat_map(PoolTree::in_tree);
+        let mut cache_report = pool_tree
+            .iter()
+            .map(|(pool, pool_tree)| (pool.clone(), pool_tree.report.clone()))
+            .collect::<Vec<_>>();
+        cache_report.sort_by_key(|(pool, _)| pool.to_string());
+        let block_list_tree = self.block_list_tree.take();
+        let main = !self.block_list.is_empty() || block_list_tree.is_some();
+        if self.mode.exclusion() && !main && self.named_block_list.is_empty() {
+            bail!("An exclusion proof needs a blocklist.");
+        }
+        let mut resolution = resolve_block_list(&self.block_list, deposit_list())?;
+        // without the blocklist of `new` only the named blocklists are proven, an empty tree
+        // would claim an exclusion from nothing
+        let main_innocence_tree = match block_list_tree {
+            Some(tree) => self.mode.exclusion().then_some(tree),
+            None => (self.mode.exclusion() && main)
+                .then(|| self.innocence_tree(None, &resolution))
+                .transpose()?,
+        };
+        if let Some(signed) = &self.manifest {
+            let manifest = &signed.manifest;
+            let tree = main_innocence_tree
+                .as_ref()
+                .ok_or(anyhow!("A blocklist manifest needs an exclusion proof."))?;
+            if tree.root() != manifest.root || tree.key_list().len() as u64 != manifest.entry_count
+            {
+                bail!(
+                    "The blocklist does not match manifest 0x{} of {} version {}, its commitments may differ or resolve to other ones.",
+                    to_hash_str(&manifest.id()),
+                    manifest.issuer,
+                    manifest.version
+                );
+            }
+        }
+        let mut named_innocence_tree = vec![];
+        for (name, block_list) in &self.named_block_list {
+            let mut named_resolution = resolve_block_list(block_list, deposit_list())?;
+            named_resolution
+                .iter_mut()
+                .for_each(|resolution| resolution.list = Some(name.clone()));
+            named_innocence_tree.push((name, self.innocence_tree(Some(name), &named_resolution)?));
+            resolution.extend(named_resolution);
+        }
+        let association_tree = self
+            .mode
+            .inclusion()
+            .then(|| TornadoMerkleTree::new(self.allow_list.clone()))
+            .transpose()
+            .map_err(|err| anyhow!("The allowlist is invalid: {err}"))?;
+
+        self.note_list
+            .iter()
+            .map(|note| {
+                let pool_tree = &pool_tree[note.pool()];
+                let PoolTree {
+                    tree: accuracy_tree,
+                    snapshot,
+                    ..
+                } = pool_tree;
+                let deposit_list = pool_tree.in_tree();
                 let commitment = to_hash(note.commitment());
-                let index = accuracy_tree.leafs()
-                    - accuracy_tree[..accuracy_tree.leafs()]
+                let index = accuracy_tree.index_of(&commitment).ok_or_else(|| {
+                    let cached = pool_tree
+                        .deposit_list
                         .iter()
-                        .rev()
-                        .position(|a| a == &commitment)
-                        .ok_or(anyhow!(
+                        .any(|log| to_hash(log.commitment.trim_start_matches("0x")) == commitment);
+                    match self.min_delay {
+                        // in the cache but after the latest tree old enough for the withdrawals
+                        Some(min_delay) if cached && self.as_of.is_none() => {
+                            withdrawn_early(note, min_delay, snapshot.block_number)
+                        }
+                        _ => anyhow!(
                             "Deposit log not exist in history, please check the cache file."
-                        ))?
-                    - 1;
+                        ),
+                    }
+                })?;
                 let (accuracy_proof_element, accuracy_proof_index) = accuracy_tree.prove(index);
+                let (association_proof_element, association_proof_index) = match &association_tree {
+                    Some(tree) => tree.prove(
+                        tree.index_of(&commitment)
+                            .ok_or(anyhow!("Commitment not exist in the allow list."))?,
+                    ),
+                    None => (vec![], vec![]),
+                };
+                let nullifier_hash = to_hash(note.nullifier_hash());
+                let withdrawal = match withdraw_log_cache.get(note.pool()) {
+                    Some(log_list) => Some(
+                        find_withdrawal(log_list, &nullifier_hash)
+                            .cloned()
+                            .ok_or_else(|| {
+                                anyhow!(
+                                    "Note of commitment {} is not withdrawn.",
+                                    note.commitment()
+                                )
+                            })?,
+                    ),
+                    None => None,
+                };
+                // withdrawals in the blocks strictly between, the order inside a block is unknown
+                let withdrawal_between = withdrawal.as_ref().map(|withdrawal| {
+                    withdraw_log_cache[note.pool()]
+                        .iter()
+                        .filter(|log| {
+                            log.block_number > deposit_list[index].block_number
+                                && log.block_number < withdrawal.block_number
+                        })
+                        .count()
+                });
+                let anonymity_set = AnonymitySet {
+                    deposit_before: index,
+                    deposit_after: snapshot.leaf_count - index - 1,
+                    withdrawal_between,
+                };
+                if let (Some(min_delay), Some(withdrawal)) = (self.min_delay, &withdrawal) {
+                    if matches!(min_delay, MinDelay::Second(_))
+                        && (withdrawal.timestamp.is_none() || snapshot.timestamp.is_none())
+                    {
+                        return Err(no_timestamp(note.pool()));
+                    }
+                    let anchor =
+                        delay_anchor(snapshot.block_number, snapshot.timestamp, withdrawal);
+                    if !min_delay.check(&anchor) {
+                        return Err(withdrawn_early(note, min_delay, snapshot.block_number));
+                    }
+                }
 
                 Ok(Proof {
                     commitment,
+                    nullifier_hash,
                     accuracy_tree_root: accuracy_tree.root(),
-                    innocence_tree_root: innocence_tree.root(),
+                    snapshot: *snapshot,
+                    pool: format!(
+                        "{}-{}-{}",
+                        note.pool().currency,
+                        note.pool().amount,
+                        note.pool().net_id
+                    ),
+                    innocence_tree_root: main_innocence_tree.as_ref().map(|tree| tree.root()),
+                    association_tree_root: association_tree.as_ref().map(|tree| tree.root()),
                     accuracy_proof_element,
                     accuracy_proof_index,
-                    innocence_proof: innocence_tree.prove(commitment),
+                    innocence_proof: main_innocence_tree
+                        .as_ref()
+                        .map(|tree| tree.prove(commitment)),
+                    association_proof_element,
+                    association_proof_index,
+                    withdrawal,
+                    min_delay: self.min_delay,
+                    anonymity_set,
+                    named_innocence: named_innocence_tree
+                        .iter()
+                        .map(|(name, tree)| NamedInnocence {
+                            name: name.to_string(),
+                            root: tree.root(),
+                            proof: tree.prove(commitment),
+                        })
+                        .collect(),
+                    manifest: self.manifest.clone(),
                 })
-            });
-        }
+            })
+            .collect::<Result<Vec<Proof>>>()
+            .map(|proof| ProveOutput {
+                proof,
+                resolution,
+                cache_report,
+            })
+            .map_err(|err| anyhow!("Failed to generate a proof for some Notes.{err}"))
+    }
 
-        let proof_list = task_list
-            .collect::<Vec<Result<Proof>>>()
+    // the deposit tree of every pool in the note list as of the snapshot
+    async fn pool_tree(
+        &self,
+        withdraw_log_cache: &HashMap<Pool, Vec<WithdrawLog>>,
+    ) -> Result<HashMap<Pool, PoolTree>> {
+        let source = &self.source;
+        let pool_list = self
+            .note_list
+            .iter()
+            .map(Note::pool)
+            .collect::<HashSet<_>>();
+
+        pool_list
+            .into_iter()
+            .map(|pool| async move {
+                let mut log_list = pool.read_deposit_log(source).await?;
+                let report = check_deposit_log(&mut log_list);
+                if self.strict && !report.is_ok() {
+                    bail!("The deposit cache of {pool} is damaged: {report}.");
+                }
+                let leaves = log_list
+                    .iter()
+                    .map(|log| to_hash(log.commitment.trim_start_matches("0x")))
+                    .collect::<Vec<Hash>>();
+                let path = self.tree_dir.as_ref().map(|dir| {
+                    dir.join(format!(
+                        "{}_{}_{}.tree",
+                        pool.net_id, pool.currency, pool.amount
+                    ))
+                });
+
+                // only the deposits after the saved tree are hashed
+                let mut tree = match &path {
+                    Some(path) if path.exists() => TornadoMerkleTree::from_bytes(&fs::read(path)?)?,
+                    _ => TornadoMerkleTree::default(),
+                };
+                // every saved leaf should still be in the cache at the same index
+                let stale = leaves.get(..tree.len()) != Some(tree.leaves());
+                if stale {
+                    bail!(
+                        "The saved tree of {pool} does not match the cache, remove `{}` to rebuild it.",
+                        path.unwrap().display()
+                    );
+                }
+                for leaf in &leaves[tree.len()..] {
+                    tree.append(*leaf)?;
+                }
+                if let Some(path) = &path {
+                    fs::write(path, tree.to_bytes())?;
+                }
+
+                let leaf_count = match self.as_of {
+                    Some(AsOf::Block(block_number)) => log_list
+                        .iter()
+                        .take_while(|log| log.block_number <= block_number)
+                        .count(),
+                    Some(AsOf::LeafCount(count)) if count > log_list.len() => bail!(
+                        "Only {} deposits of {pool} in the cache, fewer than {count}.",
+                        log_list.len()
+                    ),
+                    Some(AsOf::LeafCount(count)) => count,
+                    None => match self.min_delay {
+                        // the latest tree old enough for the earliest withdrawal of the pool
+                        Some(min_delay) => {
+                            let withdrawal = self
+                                .note_list
+                                .iter()
+                                .filter(|note| note.pool() == pool)
+                                .filter_map(|note| {
+                                    find_withdrawal(
+                                        &withdraw_log_cache[pool],
+                                        &to_hash(note.nullifier_hash()),
+                                    )
+                                })
+                                .collect::<Vec<_>>();
+                            if matches!(min_delay, MinDelay::Second(_))
+                                && withdrawal.iter().any(|withdrawal| withdrawal.timestamp.is_none())
+                            {
+                                return Err(no_timestamp(pool));
+                            }
+                            log_list
+                                .iter()
+                                .take_while(|log| {
+                                    withdrawal.iter().all(|withdrawal| {
+                                        let timestamp = log.timestamp.parse().ok();
+                                        min_delay.check(&delay_anchor(
+                                            log.block_number,
+                                            timestamp,
+                                            withdrawal,
+                                        ))
+                                    })
+                                })
+                                .count()
+                        }
+                        None => log_list.len(),
+                    },
+                };
+                tree.truncate(leaf_count);
+
+                let root = tree.root();
+                let known_root = self
+                    .known_root
+                    .iter()
+                    .filter(|(known_pool, _)| known_pool.iter().all(|known| known == pool))
+                    .map(|(_, root)| root)
+                    .collect::<Vec<_>>();
+                if !known_root.is_empty() && !known_root.contains(&&root) {
+                    bail!(
+                        "Deposit tree root 0x{} of {pool} is unknown, the cache is outdated or damaged.",
+                        to_hash_str(&root)
+                    );
+                }
+                let snapshot = Snapshot {
+                    leaf_count,
+                    block_number: match leaf_count {
+                        0 => 0,
+                        count => log_list[count - 1].block_number,
+                    },
+                    timestamp: match leaf_count {
+                        0 => None,
+                        count => log_list[count - 1].timestamp.parse().ok(),
+                    },
+                    matched_root: (!known_root.is_empty()).then_some(root),
+                };
+                let pool_tree = PoolTree {
+                    tree,
+                    snapshot,
+                    deposit_list: log_list,
+                    report,
+                };
+                Ok((pool.clone(), pool_tree))
+            })
+            .collect::<FuturesUnordered<_>>()
+            .collect::<Vec<Result<_>>>()
             .await
             .into_iter()
-            .map(|r| r.map_err(|err| anyhow!("Failed to generate a proof for some Notes.{err}")))
-            .collect::<Result<Vec<Proof>, _>>()?;
+            .collect::<Result<HashMap<_, _>>>()
+            .map_err(|err| anyhow!("Failed to generate a proof for some Notes.{err}"))
+    }
+
+    // with a tree directory the blocklist tree is kept in `blocklist[_<name>].smt` with its latest
+    // root next to it, and only the entries changed since the last run are inserted or removed
+    fn innocence_tree(
+        &self,
+        name: Option<&str>,
+        resolution: &[Resolution],
+    ) -> Result<SparseMerkleTree> {
+        let commitment_list = resolution
+            .iter()
+            .flat_map(|resolution| resolution.commitment.clone());
+        let Some(dir) = &self.tree_dir else {
+            return Ok(SparseMerkleTree::new(commitment_list.collect()));
+        };
+        let file = match name {
+            Some(name) => format!("blocklist_{name}"),
+            None => "blocklist".into(),
+        };
+        let root_path = dir.join(format!("{file}.root"));
+        let root = match fs::read_to_string(&root_path) {
+            Ok(root) => try_to_hash(root.trim().trim_start_matches("0x"))
+                .ok_or(anyhow!("Tree root `{}` is broken", root_path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => [0; 32],
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut tree = SparseMerkleTree::open(dir.join(format!("{file}.smt")), root)?;
+        let key_list = commitment_list
+            .map(|commitment| to_hash(&commitment))
+            .collect::<HashSet<_>>();
+        let saved_key_list = tree.key_list().into_iter().collect::<HashSet<_>>();
+        for key in saved_key_list.difference(&key_list) {
+            tree.remove(*key);
+        }
+        for key in key_list.difference(&saved_key_list) {
+            tree.insert(*key);
+        }
+        tree.save()?;
+        fs::write(root_path, format!("0x{}", to_hash_str(&tree.root())))?;
+
+        Ok(tree)
+    }
+
+    // the withdrawals of every pool in the note list, empty unless proofs link to them
+    async fn withdraw_log_cache(&self) -> Result<HashMap<Pool, Vec<WithdrawLog>>> {
+        let mut withdraw_log_cache = HashMap::new();
+        if !self.withdrawal && self.min_delay.is_none() {
+            return Ok(withdraw_log_cache);
+        }
+
+        for pool in self.note_list.iter().map(Note::pool) {
+            if !withdraw_log_cache.contains_key(pool) {
+                let log_list = pool.read_withdraw_log(&self.source).await?;
+                withdraw_log_cache.insert(pool.clone(), log_list);
+            }
+        }
+        Ok(withdraw_log_cache)
+    }
+
+    /// Check the deposit cache of every pool in the note list.
+    pub async fn check_cache(&self) -> Result<Vec<(Pool, CacheReport)>> {
+        let mut pool_list: Vec<&Pool> = vec![];
+        for pool in self.note_list.iter().map(Note::pool) {
+            if !pool_list.contains(&pool) {
+                pool_list.push(pool);
+            }
+        }
 
-        Ok(proof_list)
+        let mut report_list = vec![];
+        for pool in pool_list {
+            let mut log_list = pool.read_deposit_log(&self.source).await?;
+            report_list.push((pool.clone(), check_deposit_log(&mut log_list)));
+        }
+        Ok(report_list)
     }
 
     fn parse_note(mut self, list: Vec<String>) -> Result<Self> {
         self.note_list = list
             .iter()
-            .map(|note| Note::new(note, &self.util))
+            .map(|note| Note::new(note))
             .collect::<Result<Vec<Note>>>()?;
 
         Ok(self)
@@ -112,4 +536,621 @@ impl Tornado {
         self.block_list = block_list;
         self
     }
+
+    /// Prove exclusion from each named blocklist separately, besides the blocklist of `new`.
+    pub fn set_named_block_list(mut self, block_list: Vec<(String, Vec<String>)>) -> Result<Self> {
+        for (i, (name, _)) in block_list.iter().enumerate() {
+            // the name is part of the file of the saved tree
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                bail!("Blocklist name `{name}` should only have letters, digits, `_` and `-`.");
+            }
+            if block_list[..i].iter().any(|(other, _)| other == name) {
+                bail!("Blocklist name `{name}` is used more than once.");
+            }
+        }
+        self.named_block_list = block_list;
+        Ok(self)
+    }
+
+    /// Vouch for the blocklist of `new` by the manifest its issuer signed, the signature is checked
+    /// here and the root when proving.
+    pub fn set_manifest(mut self, manifest: SignedManifest) -> Result<Self> {
+        if !manifest.verify() {
+            bail!(
+                "The signature of blocklist manifest 0x{} is invalid.",
+                to_hash_str(&manifest.manifest.id())
+            );
+        }
+        self.manifest = Some(manifest);
+        Ok(self)
+    }
+
+    /// Prove against the blocklist tree of `root` in the store file instead of a blocklist, e.g.
+    /// one saved by `set_tree_dir` or updated by `SparseMerkleTree::insert` and `remove`. The
+    /// entries are taken as they are, they do not resolve through the deposits.
+    pub fn set_block_list_tree(mut self, path: impl Into<PathBuf>, root: &str) -> Result<Self> {
+        if !self.block_list.is_empty() {
+            bail!("A stored blocklist tree replaces the blocklist, give no other one.");
+        }
+        let root = try_to_hash(root.trim_start_matches("0x"))
+            .ok_or(anyhow!("Root `{root}` should be 32 bytes of hex"))?;
+        self.block_list_tree = Some(SparseMerkleTree::open(path, root)?);
+        Ok(self)
+    }
+
+    pub fn set_allow_list(mut self, allow_list: Vec<String>) -> Self {
+        self.allow_list = allow_list;
+        self
+    }
+
+    pub fn set_mode(mut self, mode: ProofMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Fail instead of proving on a deposit cache with gaps, duplicates or stale ranges, by
+    /// default the deposits are only sorted by leaf index.
+    pub fn set_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Only prove against these roots of the deposit tree, e.g. the last roots of the contract,
+    /// a root without pool applies to every pool. The pools without a known root are unchecked.
+    pub fn set_known_root(mut self, root_list: Vec<(Option<Pool>, String)>) -> Result<Self> {
+        self.known_root = root_list
+            .into_iter()
+            .map(|(pool, root)| {
+                try_to_hash(strip_hex_prefix(&root))
+                    .map(|root| (pool, root))
+                    .ok_or(anyhow!("Root `{root}` should be 32 bytes of hex"))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(self)
+    }
+
+    /// Find the withdrawal of every note by its nullifier hash and record it in the proof, so the
+    /// proof speaks for that withdrawal, notes not withdrawn yet fail.
+    pub fn set_withdrawal(mut self, withdrawal: bool) -> Self {
+        self.withdrawal = withdrawal;
+        self
+    }
+
+    /// Claim that every note is withdrawn at least `min_delay` after its deposit, this links the
+    /// proofs to the withdrawals too. A delay in seconds needs the withdrawal timestamps, which
+    /// the tornado-cli cache does not keep.
+    pub fn set_min_delay(mut self, min_delay: MinDelay) -> Self {
+        self.min_delay = Some(min_delay);
+        self
+    }
+
+    /// Prove against an older deposit tree, the proofs record the `Snapshot` they use.
+    pub fn set_as_of(mut self, as_of: AsOf) -> Self {
+        self.as_of = Some(as_of);
+        self
+    }
+
+    /// Save the deposit tree of every pool and the blocklist trees to `dir` and reuse them next
+    /// time, the directory must exist.
+    pub fn set_tree_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.tree_dir = Some(dir.into());
+        self
+    }
+}
+
+// the latest withdrawal of the nullifier hash
+#[cfg(feature = "std")]
+fn find_withdrawal<'a>(
+    log_list: &'a [WithdrawLog],
+    nullifier_hash: &Hash,
+) -> Option<&'a WithdrawLog> {
+    log_list.iter().rfind(|log| {
+        try_to_hash(log.nullifier_hash.trim_start_matches("0x")) == Some(*nullifier_hash)
+    })
+}
+
+#[cfg(feature = "std")]
+fn no_timestamp(pool: &Pool) -> anyhow::Error {
+    anyhow!("The cache of {pool} has no timestamp, only a delay in blocks can be proven.")
+}
+
+#[cfg(feature = "std")]
+fn withdrawn_early(note: &Note, min_delay: MinDelay, block_number: u32) -> anyhow::Error {
+    anyhow!(
+        "Note of commitment {} is withdrawn less than {min_delay} after the deposit tree as of block {block_number}.",
+        note.commitment()
+    )
+}
+
+#[cfg(feature = "std")]
+fn delay_anchor(
+    block_number: u32,
+    timestamp: Option<u64>,
+    withdrawal: &WithdrawLog,
+) -> DelayAnchor {
+    DelayAnchor {
+        snapshot_block: block_number,
+        snapshot_timestamp: timestamp,
+        withdrawal_block: withdrawal.block_number,
+        withdrawal_timestamp: withdrawal
+            .timestamp
+            .as_deref()
+            .and_then(|timestamp| timestamp.parse().ok()),
+    }
+}
+
+/// Check the public blocks of the `min_delay` claim of a proof against the caches of `source`:
+/// the deposit tree as of the snapshot block has the root of the proof, and the note of its
+/// nullifier hash is withdrawn in the withdrawal block. The zkVM proves the claim only relative to
+/// these blocks, a verifier should call this with a source it trusts, or check them on-chain.
+#[cfg(feature = "std")]
+pub async fn check_delay_anchor(proof: &Proof, source: &impl EventLogSource) -> Result<()> {
+    let anchor = DelayAnchor::new(proof).ok_or(anyhow!("The proof has no withdrawal."))?;
+    let pool = proof.pool.parse::<Pool>()?;
+
+    let mut deposit_list = pool.read_deposit_log(source).await?;
+    check_deposit_log(&mut deposit_list);
+    let leaf_count = deposit_list
+        .iter()
+        .take_while(|log| log.block_number <= anchor.snapshot_block)
+        .count();
+    let last = leaf_count
+        .checked_sub(1)
+        .map(|index| &deposit_list[index])
+        .filter(|log| log.block_number == anchor.snapshot_block)
+        .ok_or(anyhow!(
+            "No deposit of {pool} in block {}, the snapshot is not of the pool.",
+            anchor.snapshot_block
+        ))?;
+    if anchor.snapshot_timestamp.is_some()
+        && last.timestamp.parse().ok() != anchor.snapshot_timestamp
+    {
+        bail!(
+            "The snapshot timestamp differs from the one of block {}.",
+            anchor.snapshot_block
+        );
+    }
+    let tree = TornadoMerkleTree::new(
+        deposit_list[..leaf_count]
+            .iter()
+            .map(|log| log.commitment.trim_start_matches("0x").to_string())
+            .collect(),
+    )?;
+    if tree.root() != proof.accuracy_tree_root {
+        bail!(
+            "The deposit tree of {pool} as of block {} has another root than the proof.",
+            anchor.snapshot_block
+        );
+    }
+
+    let withdraw_list = pool.read_withdraw_log(source).await?;
+    let withdrawal = find_withdrawal(&withdraw_list, &proof.nullifier_hash).ok_or(anyhow!(
+        "The note of nullifier hash 0x{} is not withdrawn.",
+        to_hash_str(&proof.nullifier_hash)
+    ))?;
+    let timestamp = withdrawal
+        .timestamp
+        .as_deref()
+        .and_then(|timestamp| timestamp.parse().ok());
+    if withdrawal.block_number != anchor.withdrawal_block
+        || anchor.withdrawal_timestamp.is_some() && timestamp != anchor.withdrawal_timestamp
+    {
+        bail!(
+            "The withdrawal of nullifier hash 0x{} in block {} is not the one of the proof.",
+            to_hash_str(&proof.nullifier_hash),
+            withdrawal.block_number
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixture::*;
+    use wasm_bindgen_test::*;
+
+    fn tornado(source: MemorySource) -> Tornado<MemorySource> {
+        Tornado::new(vec![NOTE.into()], vec![OTHER_HASH.into()], source).unwrap()
+    }
+
+    async fn prove(as_of: Option<AsOf>) -> Result<Vec<Proof>> {
+        let mut tornado = tornado(deposit_source());
+        tornado.as_of = as_of;
+        tornado.prove().await
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_as_of() {
+        let latest = prove(None).await.unwrap();
+        let old = prove(Some(AsOf::Block(8525389))).await.unwrap();
+
+        assert_eq!(
+            latest[0].snapshot,
+            Snapshot {
+                leaf_count: 2,
+                block_number: 8525390,
+                timestamp: Some(1676880012),
+                matched_root: None,
+            }
+        );
+        assert_eq!(
+            old[0].snapshot,
+            Snapshot {
+                leaf_count: 1,
+                block_number: 8525389,
+                timestamp: Some(1676880000),
+                matched_root: None,
+            }
+        );
+        assert_eq!(latest[0].anonymity_set.deposit_after, 1);
+        assert_eq!(old[0].anonymity_set.deposit_after, 0);
+        assert_ne!(latest[0].accuracy_tree_root, old[0].accuracy_tree_root);
+        assert_eq!(prove(Some(AsOf::LeafCount(1))).await.unwrap(), old);
+        assert!(prove(Some(AsOf::LeafCount(3))).await.is_err());
+        assert!(prove(Some(AsOf::Block(8525388))).await.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_known_root() {
+        let root = to_hash_str(&prove(None).await.unwrap()[0].accuracy_tree_root);
+        let other_pool = "eth-1-5".parse::<Pool>().unwrap();
+        let prove = |root_list: Vec<(Option<Pool>, String)>| async move {
+            tornado(deposit_source())
+                .set_known_root(root_list)?
+                .prove()
+                .await
+        };
+
+        let proof = prove(vec![(None, OTHER_HASH.into()), (None, format!("0x{root}"))])
+            .await
+            .unwrap();
+        assert_eq!(proof[0].snapshot.matched_root, Some(to_hash(&root)));
+        assert!(prove(vec![(None, OTHER_HASH.into())]).await.is_err());
+        // a pool without known roots is not checked
+        let proof = prove(vec![(Some(other_pool), OTHER_HASH.into())])
+            .await
+            .unwrap();
+        assert_eq!(proof[0].snapshot.matched_root, None);
+        assert!(prove(vec![(None, "0xroot".into())]).await.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_withdrawal() {
+        let tornado = |withdraw_log| {
+            tornado(deposit_source().insert(WITHDRAW_PATH, withdraw_log)).set_withdrawal(true)
+        };
+        assert!(tornado("[]").prove().await.is_err());
+
+        let mut proof = tornado(WITHDRAW_LOG).prove().await.unwrap();
+        let withdrawal = proof[0].withdrawal.as_ref().unwrap();
+        assert_eq!(withdrawal.block_number, 8525500);
+        assert_eq!(
+            proof[0].anonymity_set,
+            AnonymitySet {
+                deposit_before: 0,
+                deposit_after: 1,
+                withdrawal_between: Some(0),
+            }
+        );
+        assert!(crate::verify(proof.clone()));
+
+        proof[0].withdrawal.as_mut().unwrap().nullifier_hash = format!("0x{OTHER_HASH}");
+        assert!(!crate::verify(proof));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_min_delay() {
+        let source = deposit_source().insert(WITHDRAW_PATH, WITHDRAW_LOG);
+        let prove_from = |source, min_delay| tornado(source).set_min_delay(min_delay).prove();
+        let prove = |min_delay| prove_from(source.clone(), min_delay);
+
+        // the latest tree old enough is taken, the one with the deposit of the note only
+        let mut proof = prove(MinDelay::Block(111)).await.unwrap();
+        assert_eq!(proof[0].snapshot.leaf_count, 1);
+        assert_eq!(proof[0].snapshot.block_number, 8525389);
+        assert_eq!(proof[0].pool, "eth-0.1-5");
+        assert!(crate::verify(proof.clone()));
+        check_delay_anchor(&proof[0], &source).await.unwrap();
+        assert_eq!(
+            Journal::new(&proof).unwrap().delay_anchor,
+            vec![DelayAnchor {
+                snapshot_block: 8525389,
+                snapshot_timestamp: Some(1676880000),
+                withdrawal_block: 8525500,
+                withdrawal_timestamp: Some(1676881332),
+            }]
+        );
+        let proof_110 = prove(MinDelay::Block(110)).await.unwrap();
+        assert_eq!(proof_110[0].snapshot.leaf_count, 2);
+        check_delay_anchor(&proof_110[0], &source).await.unwrap();
+        let err = prove(MinDelay::Block(112)).await.unwrap_err();
+        assert!(err.to_string().contains("withdrawn less than 112 blocks"));
+
+        // 1332 and 1320 seconds after the deposits
+        let proof_1321 = prove(MinDelay::Second(1321)).await.unwrap();
+        assert_eq!(proof_1321[0].snapshot.leaf_count, 1);
+        assert!(crate::verify(proof_1321));
+        let proof_1320 = prove(MinDelay::Second(1320)).await.unwrap();
+        assert_eq!(proof_1320[0].snapshot.leaf_count, 2);
+        assert!(prove(MinDelay::Second(1333)).await.is_err());
+        let untimed = WITHDRAW_LOG.replace(r#","timestamp":"1676881332""#, "");
+        let untimed = deposit_source().insert(WITHDRAW_PATH, &untimed);
+        let err = prove_from(untimed.clone(), MinDelay::Second(1))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("has no timestamp"));
+        assert!(prove_from(untimed, MinDelay::Block(111)).await.is_ok());
+
+        // a deposit missing from the cache is not blamed on the delay
+        let missing = MemorySource::new()
+            .insert(
+                DEPOSIT_PATH,
+                &DEPOSIT_LOG.replace(COMMITMENT_HASH, OTHER_HASH),
+            )
+            .insert(WITHDRAW_PATH, WITHDRAW_LOG);
+        let err = prove_from(missing, MinDelay::Block(1)).await.unwrap_err();
+        assert!(err.to_string().contains("not exist in history"));
+
+        proof[0].min_delay = Some(MinDelay::Block(112));
+        assert!(!crate::verify(proof.clone()));
+        proof[0].min_delay = Some(MinDelay::Block(111));
+
+        // an earlier snapshot passes in the zkVM, but the tree as of it has another root
+        let mut forged = proof.clone();
+        forged[0].snapshot.block_number = 8000000;
+        forged[0].min_delay = Some(MinDelay::Block(500000));
+        assert!(crate::verify(forged.clone()));
+        assert!(check_delay_anchor(&forged[0], &source).await.is_err());
+        forged[0].snapshot.block_number = 8525390;
+        assert!(check_delay_anchor(&forged[0], &source).await.is_err());
+
+        proof[0].withdrawal.as_mut().unwrap().block_number = 8525600;
+        assert!(check_delay_anchor(&proof[0], &source).await.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_allow_list() {
+        let mut proof = tornado(deposit_source())
+            .set_allow_list(vec![COMMITMENT_HASH.into(), OTHER_HASH.into()])
+            .set_mode(ProofMode::Both)
+            .prove()
+            .await
+            .unwrap();
+        assert!(crate::verify(proof.clone()));
+
+        // the commitment as its own association tree
+        let commitment = proof[0].commitment;
+        proof[0].association_tree_root = Some(commitment);
+        proof[0].association_proof_element = vec![commitment, commitment];
+        proof[0].association_proof_index = vec![];
+        assert!(!crate::verify(proof));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_block_list_resolution() {
+        let prove = |block_list: Vec<String>| {
+            tornado(deposit_source())
+                .set_block_list(block_list)
+                .prove_with_report()
+        };
+
+        // the deposit transaction of the note
+        let ProveOutput {
+            proof,
+            resolution,
+            cache_report,
+        } = prove(vec![
+            "0x06e10a9ea49183e9127fb7581d4d54750290c1ecc7c7f1707953f706fe9ab959".into(),
+        ])
+        .await
+        .unwrap();
+        assert!(cache_report[0].1.is_ok());
+        assert_eq!(resolution[0].kind, Some(EntryKind::Transaction));
+        assert!(!crate::verify(proof));
+
+        let ProveOutput {
+            proof, resolution, ..
+        } = prove(vec![OTHER_HASH.into()]).await.unwrap();
+        assert_eq!(resolution[0].kind, None);
+        assert!(crate::verify(proof));
+
+        // the other deposit, its transaction hash is out of the field
+        let block_list = BlockList::new()
+            .parse(
+                "0x7b5ee6c14b86509c2b401ee1ec15657f303494acfe5d786cc4081a6666f34414",
+                BlockListFormat::Lines,
+                "-",
+            )
+            .unwrap();
+        let ProveOutput {
+            proof, resolution, ..
+        } = prove(block_list.value_list()).await.unwrap();
+        assert_eq!(resolution[0].kind, Some(EntryKind::Transaction));
+        assert!(crate::verify(proof));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_named_block_list() {
+        let prove = |named: Vec<(&str, &str)>| {
+            tornado(deposit_source())
+                .set_named_block_list(
+                    named
+                        .into_iter()
+                        .map(|(name, entry)| (name.to_string(), vec![entry.to_string()]))
+                        .collect(),
+                )
+                .map(Tornado::prove)
+        };
+        let proof = prove(vec![("ofac", OTHER_HASH), ("community", OTHER_HASH)])
+            .unwrap()
+            .await
+            .unwrap();
+        let named_root = Journal::new(&proof).unwrap().named_innocence_root;
+        assert_eq!(named_root[0].0, "ofac");
+        assert_eq!(named_root[1].0, "community");
+        assert!(crate::verify(proof));
+
+        let proof = prove(vec![("ofac", OTHER_HASH), ("community", COMMITMENT_HASH)])
+            .unwrap()
+            .await
+            .unwrap();
+        assert!(!crate::verify(proof));
+        assert!(prove(vec![("ofac", OTHER_HASH), ("ofac", OTHER_HASH)]).is_err());
+        assert!(prove(vec![("../ofac", OTHER_HASH)]).is_err());
+
+        // only a named blocklist, the proof claims nothing about an empty main one
+        let named_only = |named: Vec<(String, Vec<String>)>| {
+            tornado(deposit_source())
+                .set_block_list(vec![])
+                .set_named_block_list(named)
+                .unwrap()
+                .prove()
+        };
+        let proof = named_only(vec![("ofac".into(), vec![OTHER_HASH.into()])])
+            .await
+            .unwrap();
+        assert_eq!(proof[0].innocence_tree_root, None);
+        assert_eq!(proof[0].innocence_proof, None);
+        assert_eq!(proof[0].mode(), Some(ProofMode::Exclusion));
+        assert!(crate::verify(proof));
+        assert!(named_only(vec![]).await.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_manifest() {
+        let manifest = |block_list: &str| {
+            BlockList::new()
+                .parse(block_list, BlockListFormat::Lines, "-")
+                .unwrap()
+                .manifest("ofac", 1, 1676880000)
+                .unwrap()
+                .sign(&[7; 32])
+        };
+        let signed = manifest(OTHER_HASH);
+        let proof = tornado(deposit_source())
+            .set_manifest(signed.clone())
+            .unwrap()
+            .prove()
+            .await
+            .unwrap();
+        let journal = Journal::new(&proof).unwrap();
+        assert_eq!(journal.issuer_key, Some(signed.public_key));
+        assert_eq!(journal.manifest, Some(signed.manifest.clone()));
+        assert!(crate::verify(proof.clone()));
+
+        // a manifest moved to another root fails to verify
+        let mut forged = proof;
+        forged[0].manifest.as_mut().unwrap().manifest.root = [0; 32];
+        assert!(!crate::verify(forged));
+        assert!(tornado(deposit_source())
+            .set_manifest(SignedManifest {
+                signature: manifest(COMMITMENT_HASH).signature,
+                ..signed
+            })
+            .is_err());
+        assert!(tornado(deposit_source())
+            .set_manifest(manifest(COMMITMENT_HASH))
+            .unwrap()
+            .prove()
+            .await
+            .is_err());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_saved_block_list_tree() {
+        let dir = std::env::temp_dir().join(format!("poi_tree_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let prove = |block_list: Vec<&str>| {
+            tornado(deposit_source())
+                .set_block_list(block_list.into_iter().map(String::from).collect())
+                .set_tree_dir(&dir)
+                .prove()
+        };
+        let root = |block_list: Vec<&str>| {
+            SparseMerkleTree::new(block_list.into_iter().map(String::from).collect()).root()
+        };
+        let third_hash = "1";
+
+        // the saved tree takes the entries added and removed since the last run
+        for block_list in [
+            vec![OTHER_HASH],
+            vec![OTHER_HASH, third_hash],
+            vec![third_hash],
+        ] {
+            let proof = prove(block_list.clone()).await.unwrap();
+            assert_eq!(proof[0].innocence_tree_root, Some(root(block_list)));
+            assert!(crate::verify(proof));
+        }
+        assert_eq!(
+            fs::read_to_string(dir.join("blocklist.root")).unwrap(),
+            format!("0x{}", to_hash_str(&root(vec![third_hash])))
+        );
+
+        // any saved root is reopened without the list, and updated by its own
+        let store = dir.join("blocklist.smt");
+        let prove_stored = |root: Hash| {
+            tornado(deposit_source())
+                .set_block_list(vec![])
+                .set_block_list_tree(&store, &to_hash_str(&root))
+                .map(Tornado::prove)
+        };
+        let first_root = root(vec![OTHER_HASH]);
+        let proof = prove_stored(first_root).unwrap().await.unwrap();
+        assert_eq!(proof[0].innocence_tree_root, Some(first_root));
+        assert!(crate::verify(proof));
+
+        let mut tree = SparseMerkleTree::open(&store, first_root).unwrap();
+        tree.insert(to_hash(COMMITMENT_HASH));
+        tree.save().unwrap();
+        let proof = prove_stored(tree.root()).unwrap().await.unwrap();
+        assert!(!crate::verify(proof));
+        tree.remove(to_hash(COMMITMENT_HASH));
+        assert_eq!(tree.root(), first_root);
+
+        assert!(prove_stored([1; 32]).is_err());
+        assert!(tornado(deposit_source())
+            .set_block_list_tree(&store, &to_hash_str(&first_root))
+            .is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_damaged_cache_report() {
+        let deposit_log = DEPOSIT_LOG.replace(r#""leafIndex":1"#, r#""leafIndex":5"#);
+        let prove = |strict: bool| {
+            tornado(MemorySource::new().insert(DEPOSIT_PATH, &deposit_log))
+                .set_strict(strict)
+                .prove_with_report()
+        };
+
+        let cache_report = prove(false).await.unwrap().cache_report;
+        assert_eq!(cache_report.len(), 1);
+        assert_eq!(cache_report[0].1.missing_index, vec![1..5]);
+        assert!(prove(true).await.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_stale_saved_tree() {
+        let dir = std::env::temp_dir().join(format!("poi_stale_tree_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let prove = |deposit_log: String| {
+            tornado(MemorySource::new().insert(DEPOSIT_PATH, &deposit_log))
+                .set_tree_dir(&dir)
+                .prove()
+        };
+        prove(DEPOSIT_LOG.into()).await.unwrap();
+
+        // an earlier leaf than the last saved one changes
+        let changed = DEPOSIT_LOG.replacen(COMMITMENT_HASH, OTHER_HASH, 1);
+        let err = prove(changed).await.unwrap_err();
+        assert!(err.to_string().contains("does not match the cache"));
+        assert!(prove(DEPOSIT_LOG.into()).await.is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,344 @@
+use super::manifest::Manifest;
+use super::merkle::{
+    parse_commitment, strip_hex_prefix, to_hash_str, try_to_hash, SparseMerkleTree,
+};
+use super::typ::*;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Where a blocked value is listed, e.g. a row of a sanctions list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Provenance {
+    pub file: String,
+    // 1-based, `None` for json which has no lines per item
+    pub line: Option<usize>,
+    pub reason: Option<String>,
+    pub source: Option<String>,
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        if let Some(reason) = &self.reason {
+            write!(f, ", {reason}")?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, ", from {source}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A blocked value and every place it is listed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlockEntry {
+    // lowercase hex without `0x`, 40 digits for an address and 64 for the rest
+    pub value: HashStr,
+    pub provenance: Vec<Provenance>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockListFormat {
+    /// a header row, the value in the first column, optional `reason` and `source` columns
+    Csv,
+    /// one value per line, blank lines and `#` comments are skipped
+    Lines,
+    /// an array of values, or of objects with `value`, `reason` and `source`
+    Json,
+}
+
+impl BlockListFormat {
+    /// By the extension, `.csv`, `.json` or one value per line.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => Self::Csv,
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Self::Json,
+            _ => Self::Lines,
+        }
+    }
+}
+
+/// Blocklist files merged into one list without duplicates, the input of `Tornado`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BlockList {
+    entries: Vec<BlockEntry>,
+    // position of every value in `entries`
+    #[serde(skip)]
+    index: HashMap<HashStr, usize>,
+}
+
+impl BlockList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|err| anyhow!("Failed to read blocklist `{}`: {err}", path.display()))?;
+        self.parse(
+            &content,
+            BlockListFormat::from_path(path),
+            &path.display().to_string(),
+        )
+    }
+
+    /// `file` only names the content in the provenance and errors.
+    pub fn parse(mut self, content: &str, format: BlockListFormat, file: &str) -> Result<Self> {
+        let provenance = |line, reason, source| Provenance {
+            file: file.into(),
+            line,
+            reason,
+            source,
+        };
+
+        match format {
+            BlockListFormat::Lines => {
+                for (i, line) in content.lines().enumerate() {
+                    let line = line.trim();
+                    if !line.is_empty() && !line.starts_with('#') {
+                        self.insert(line, provenance(Some(i + 1), None, None))?;
+                    }
+                }
+            }
+            BlockListFormat::Csv => {
+                let mut reader = csv::ReaderBuilder::new()
+                    .trim(csv::Trim::All)
+                    .comment(Some(b'#'))
+                    .from_reader(content.as_bytes());
+                let mut column = |name_list: &[&str]| {
+                    reader.headers().ok().and_then(|header| {
+                        header.iter().position(|column| {
+                            name_list
+                                .iter()
+                                .any(|name| column.eq_ignore_ascii_case(name))
+                        })
+                    })
+                };
+                // without a header the first entry would be taken as one and dropped
+                let value = column(&["value", "commitment", "address", "hash"]).ok_or(anyhow!(
+                    "Blocklist `{file}` should start with a header naming its `value` column, like `value,reason,source`"
+                ))?;
+                let (reason, source) = (column(&["reason"]), column(&["source"]));
+
+                for record in reader.records() {
+                    let record = record.map_err(|err| anyhow!("Blocklist `{file}` {err}"))?;
+                    let line = record.position().map(|position| position.line() as usize);
+                    let field = |i: Option<usize>| {
+                        i.and_then(|i| record.get(i))
+                            .filter(|field| !field.is_empty())
+                            .map(String::from)
+                    };
+                    let value = record.get(value).unwrap_or_default();
+                    self.insert(value, provenance(line, field(reason), field(source)))?;
+                }
+            }
+            BlockListFormat::Json => {
+                #[derive(Deserialize)]
+                #[serde(untagged)]
+                enum Item {
+                    Value(String),
+                    Entry {
+                        value: String,
+                        reason: Option<String>,
+                        source: Option<String>,
+                    },
+                }
+
+                let item_list: Vec<Item> = serde_json::from_str(content)
+                    .map_err(|err| anyhow!("Blocklist `{file}` is invalid: {err}"))?;
+                for item in item_list {
+                    match item {
+                        Item::Value(value) => self.insert(&value, provenance(None, None, None))?,
+                        Item::Entry {
+                            value,
+                            reason,
+                            source,
+                        } => self.insert(&value, provenance(None, reason, source))?,
+                    }
+                }
+            }
+        }
+
+        Ok(self)
+    }
+
+    // an address, or a commitment or transaction hash, only commitments are in the BN254 scalar
+    // field so the field is checked once the entry is resolved
+    fn insert(&mut self, value: &str, provenance: Provenance) -> Result<()> {
+        let hex = strip_hex_prefix(value).to_lowercase();
+        let value = match (hex.len(), try_to_hash(&hex)) {
+            (40, Some(_)) => hex,
+            (_, Some(hash)) => to_hash_str(&hash),
+            (_, None) => {
+                bail!("Blocklist entry `{value}` of {provenance} is not hex of 32 bytes at most")
+            }
+        };
+
+        match self.index.get(&value) {
+            Some(&i) => self.entries[i].provenance.push(provenance),
+            None => {
+                self.index.insert(value.clone(), self.entries.len());
+                self.entries.push(BlockEntry {
+                    value,
+                    provenance: vec![provenance],
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[BlockEntry] {
+        &self.entries
+    }
+
+    pub fn value_list(&self) -> Vec<HashStr> {
+        self.entries
+            .iter()
+            .map(|entry| entry.value.clone())
+            .collect()
+    }
+
+    /// The manifest of the list for its issuer to sign. The root is the one `Tornado` proves
+    /// against only if the entries are commitments.
+    pub fn manifest(&self, issuer: &str, version: u64, timestamp: u64) -> Result<Manifest> {
+        let commitment_list = self.commitment_list()?;
+        Ok(Manifest {
+            issuer: issuer.into(),
+            version,
+            timestamp,
+            entry_count: commitment_list.len() as u64,
+            root: SparseMerkleTree::new(commitment_list.iter().map(to_hash_str).collect()).root(),
+        })
+    }
+
+    /// The entries as commitments without resolving them, addresses and values out of the BN254
+    /// scalar field are refused, other transaction hashes cannot be told from commitments.
+    pub fn commitment_list(&self) -> Result<Vec<Hash>> {
+        if let Some(entry) = self.entries.iter().find(|entry| entry.value.len() == 40) {
+            bail!(
+                "Address `0x{}` of {} resolves through the deposits, it is no commitment",
+                entry.value,
+                entry.provenance[0]
+            );
+        }
+        if let Some(entry) = self
+            .entries
+            .iter()
+            .find(|entry| parse_commitment(&entry.value).is_err())
+        {
+            bail!(
+                "Entry `0x{}` of {} is out of the BN254 scalar field, it is no commitment",
+                entry.value,
+                entry.provenance[0]
+            );
+        }
+        self.entries
+            .iter()
+            .map(|entry| parse_commitment(&entry.value))
+            .collect()
+    }
+
+    /// Where the value is listed, empty if it is not.
+    pub fn provenance(&self, value: &str) -> &[Provenance] {
+        match self.index.get(value) {
+            Some(&i) => &self.entries[i].provenance,
+            None => &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use num_traits::Num;
+    use wasm_bindgen_test::*;
+
+    const COMMITMENT: &str = "296137799075f986ce4c0bbbfdade2b96689d7720d2e8b59a84bf48f4afe9ad8";
+    const ADDRESS: &str = "8a2aa7e8b3b4ce1ee2a1a0b9b1b0f1ed0f0d8e9c";
+
+    #[wasm_bindgen_test]
+    fn test_block_list() {
+        let csv = format!(
+            "value,reason,source\n0x{},sanctioned,ofac\n# note\n0x{ADDRESS},,\n",
+            COMMITMENT.to_uppercase()
+        );
+        let lines = format!("# community\n{COMMITMENT}\n\n0x01\n");
+        let json = format!(r#"["0x{ADDRESS}", {{"value": "0x01", "reason": "hack"}}]"#);
+        let block_list = BlockList::new()
+            .parse(&csv, BlockListFormat::Csv, "ofac.csv")
+            .unwrap()
+            .parse(&lines, BlockListFormat::Lines, "community.txt")
+            .unwrap()
+            .parse(&json, BlockListFormat::Json, "exchange.json")
+            .unwrap();
+
+        assert_eq!(
+            block_list.value_list(),
+            vec![
+                COMMITMENT.to_string(),
+                ADDRESS.into(),
+                format!("{:0>64}", "1")
+            ]
+        );
+        let provenance = block_list.provenance(COMMITMENT);
+        assert_eq!(provenance.len(), 2);
+        assert_eq!(
+            provenance[0].to_string(),
+            "ofac.csv:2, sanctioned, from ofac"
+        );
+        assert_eq!(provenance[1].to_string(), "community.txt:2");
+        assert_eq!(block_list.provenance(ADDRESS)[0].reason, None);
+        assert_eq!(
+            block_list.provenance(&format!("{:0>64}", "1"))[1].reason,
+            Some("hack".into())
+        );
+
+        // a transaction hash may be out of the field, it is no commitment to sign
+        let field_size = format!("{:x}", BigUint::from_str_radix(FIELD_SIZE, 10).unwrap());
+        let tx_list = BlockList::new()
+            .parse(&field_size, BlockListFormat::Lines, "-")
+            .unwrap();
+        assert_eq!(tx_list.value_list(), vec![field_size]);
+        assert!(tx_list.manifest("ofac", 1, 0).is_err());
+        assert!(BlockList::new()
+            .parse("0xzz", BlockListFormat::Lines, "-")
+            .is_err());
+        assert!(BlockList::new()
+            .parse("0x0x01", BlockListFormat::Lines, "-")
+            .is_err());
+        assert_eq!(
+            BlockList::new()
+                .parse("0XAB", BlockListFormat::Lines, "-")
+                .unwrap()
+                .value_list(),
+            vec![format!("{:0>64}", "ab")]
+        );
+        // a csv without header loses no entry, one naming the value column elsewhere is read
+        assert!(BlockList::new()
+            .parse("0x01,sanctioned,ofac\n0x02,,\n", BlockListFormat::Csv, "-")
+            .is_err());
+        let reordered = BlockList::new()
+            .parse(
+                "reason,Commitment\nsanctioned,0x01\n",
+                BlockListFormat::Csv,
+                "-",
+            )
+            .unwrap();
+        assert_eq!(reordered.value_list(), vec![format!("{:0>64}", "1")]);
+        assert_eq!(
+            reordered.provenance(&format!("{:0>64}", "1"))[0].reason,
+            Some("sanctioned".into())
+        );
+        assert_eq!(BlockListFormat::from_path("list.CSV"), BlockListFormat::Csv);
+        assert!(block_list.manifest("ofac", 1, 0).is_err());
+    }
+}
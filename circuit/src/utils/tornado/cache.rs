@@ -0,0 +1,162 @@
+use super::typ::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+/// Problems found in the deposit cache of a pool, any of them gives a wrong deposit tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CacheReport {
+    pub deposit_count: usize,
+    // first and last block of the deposits
+    pub block_range: Option<(u32, u32)>,
+    // entries listed more than once, dropped before building the tree
+    pub repeated_entry: usize,
+    // leaf indices without a deposit, from 0 to the last one
+    pub missing_index: Vec<Range<usize>>,
+    // leaf indices of different deposits
+    pub conflict_index: Vec<usize>,
+    // commitments deposited at more than one leaf index
+    pub duplicate_commitment: Vec<(HashStr, Vec<usize>)>,
+    // (previous, leaf) index pairs where the leaf is in an earlier block than the entry before
+    // it, e.g. a stale range appended after a newer one
+    pub stale_block: Vec<(usize, usize)>,
+}
+
+impl CacheReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing_index.is_empty()
+            && self.conflict_index.is_empty()
+            && self.duplicate_commitment.is_empty()
+            && self.stale_block.is_empty()
+    }
+}
+
+impl fmt::Display for CacheReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} deposits", self.deposit_count)?;
+        if let Some((first, last)) = self.block_range {
+            write!(f, " in block {first}..={last}")?;
+        }
+        if self.repeated_entry > 0 {
+            write!(f, ", {} repeated entries", self.repeated_entry)?;
+        }
+        for range in &self.missing_index {
+            write!(f, ", missing leaf {}..{}", range.start, range.end)?;
+        }
+        for i in &self.conflict_index {
+            write!(f, ", different deposits at leaf {i}")?;
+        }
+        for (commitment, index_list) in &self.duplicate_commitment {
+            write!(f, ", commitment {commitment} at leaf {index_list:?}")?;
+        }
+        for (previous, leaf) in &self.stale_block {
+            write!(
+                f,
+                ", leaf {leaf} is in an earlier block than leaf {previous}"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Sort the deposits by leaf index and drop the repeated entries, the report tells whether the
+/// rest forms a valid tree.
+pub fn check_deposit_log(log_list: &mut Vec<DepositLog>) -> CacheReport {
+    log_list.sort_by_key(|log| log.leaf_index);
+    let count = log_list.len();
+    log_list.dedup();
+
+    let block_list = log_list.iter().map(|log| log.block_number);
+    let mut report = CacheReport {
+        deposit_count: log_list.len(),
+        block_range: block_list.clone().min().zip(block_list.max()),
+        repeated_entry: count - log_list.len(),
+        ..Default::default()
+    };
+    let mut commitment_map: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut next = 0;
+
+    for (i, log) in log_list.iter().enumerate() {
+        if log.leaf_index > next {
+            report.missing_index.push(next..log.leaf_index);
+        }
+        if i > 0 && log_list[i - 1].leaf_index == log.leaf_index {
+            report.conflict_index.push(log.leaf_index);
+        }
+        if i > 0 && log_list[i - 1].block_number > log.block_number {
+            report
+                .stale_block
+                .push((log_list[i - 1].leaf_index, log.leaf_index));
+        }
+        next = log.leaf_index + 1;
+
+        commitment_map
+            .entry(log.commitment.trim_start_matches("0x").to_lowercase())
+            .or_default()
+            .push(log.leaf_index);
+    }
+    report.conflict_index.dedup();
+    report.duplicate_commitment = commitment_map
+        .into_iter()
+        .filter(|(_, index_list)| index_list.len() > 1)
+        .collect();
+    report
+        .duplicate_commitment
+        .sort_by_key(|(_, index_list)| index_list[0]);
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn log(leaf_index: usize, block_number: u32, commitment: &str) -> DepositLog {
+        DepositLog {
+            leaf_index,
+            block_number,
+            commitment: commitment.into(),
+            ..Default::default()
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_check_deposit_log() {
+        let mut log_list = vec![log(1, 11, "0x02"), log(0, 10, "0x01"), log(1, 11, "0x02")];
+        let report = check_deposit_log(&mut log_list);
+        assert!(report.is_ok());
+        assert_eq!(report.repeated_entry, 1);
+        assert_eq!(report.block_range, Some((10, 11)));
+        assert_eq!(log_list, vec![log(0, 10, "0x01"), log(1, 11, "0x02")]);
+
+        let mut log_list = vec![
+            log(1, 11, "0x01"),
+            log(3, 12, "0x02"),
+            log(3, 12, "0x03"),
+            log(4, 9, "0x01"),
+        ];
+        let report = check_deposit_log(&mut log_list);
+        assert!(!report.is_ok());
+        assert_eq!(report.missing_index, vec![0..1, 2..3]);
+        assert_eq!(report.conflict_index, vec![3]);
+        assert_eq!(
+            report.duplicate_commitment,
+            vec![("01".to_string(), vec![1, 4])]
+        );
+        assert_eq!(report.stale_block, vec![(3, 4)]);
+        assert!(report
+            .to_string()
+            .contains("leaf 4 is in an earlier block than leaf 3"));
+
+        // the same leaf twice, the second one in an earlier block
+        let mut log_list = vec![log(0, 10, "0x01"), log(0, 9, "0x02")];
+        let report = check_deposit_log(&mut log_list);
+        assert_eq!(report.conflict_index, vec![0]);
+        assert_eq!(report.stale_block, vec![(0, 0)]);
+        assert!(report
+            .to_string()
+            .contains("leaf 0 is in an earlier block than leaf 0"));
+    }
+}
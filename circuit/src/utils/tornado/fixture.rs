@@ -0,0 +1,29 @@
+// notes and caches of the goerli 0.1 eth pool shared by the tests
+use super::MemorySource;
+
+// https://goerli.etherscan.io/tx/0x06e10a9ea49183e9127fb7581d4d54750290c1ecc7c7f1707953f706fe9ab959
+pub const NOTE: &str = r"tornado-eth-0.1-5-0xebcf5edb762e52e6eb0f33818c647cdceb75d1cd6609847ec56b750445de0b659a11796781c60aaf3ba5d693b360a77d5cff360c982ed9dc2fd419b858d3";
+pub const NULLIFIER_HASH: &str = "2d39004125a3df2cbb59ad3aa3dee045fac6f176376343632be7b9cc476ad423";
+pub const COMMITMENT_HASH: &str =
+    "296137799075f986ce4c0bbbfdade2b96689d7720d2e8b59a84bf48f4afe9ad8";
+
+// https://goerli.etherscan.io/tx/0x7b5ee6c14b86509c2b401ee1ec15657f303494acfe5d786cc4081a6666f34414
+pub const COST_NOTE: &str = r"tornado-eth-0.1-5-0x4805479a68a261e0850509d4a0724877c9395be42d78146b05880d7fd4b9484e92c8de0dfc2df89aae1a7d87726da32eed131fde50bff26a0392ce2b6729";
+pub const COST_NULLIFIER_HASH: &str =
+    "29e06ac32f5db0048ed954c971413c676bdf65bc318ee72bc52ce6162c76bf09";
+pub const COST_COMMITMENT_HASH: &str =
+    "0129af81b9bdf54d834cdef1c6aab21c5ff95e4c40f10bc3a013bd929fbc38ac";
+
+// a commitment deposited by no note
+pub const OTHER_HASH: &str = "296137799075f986ce4c0bbbfdade2b96689d7720d2e8b59a84bf48f4afe9ad9";
+
+pub const DEPOSIT_PATH: &str = "goerli/deposits_eth_0.1.json";
+pub const WITHDRAW_PATH: &str = "goerli/withdrawals_eth_0.1.json";
+// the deposits of `NOTE` and `COST_NOTE`
+pub const DEPOSIT_LOG: &str = r#"[{"blockNumber":8525389,"leafIndex":0,"transactionHash":"0x06e10a9ea49183e9127fb7581d4d54750290c1ecc7c7f1707953f706fe9ab959","commitment":"0x296137799075f986ce4c0bbbfdade2b96689d7720d2e8b59a84bf48f4afe9ad8","timestamp":"1676880000"},{"blockNumber":8525390,"leafIndex":1,"transactionHash":"0x7b5ee6c14b86509c2b401ee1ec15657f303494acfe5d786cc4081a6666f34414","commitment":"0x0129af81b9bdf54d834cdef1c6aab21c5ff95e4c40f10bc3a013bd929fbc38ac","timestamp":"1676880012"}]"#;
+// the withdrawal of `NOTE`
+pub const WITHDRAW_LOG: &str = r#"[{"blockNumber":8525500,"transactionHash":"0x3f0e08cbd1a7e7ae8e2e5cb0dc0a25e4b7ca3d6a3d4e7a7bde0a2a1d4d1b7e51","nullifierHash":"0x2d39004125a3df2cbb59ad3aa3dee045fac6f176376343632be7b9cc476ad423","to":"0x8a2aa7e8b3b4ce1ee2a1a0b9b1b0f1ed0f0d8e9c","fee":"0","timestamp":"1676881332"}]"#;
+
+pub fn deposit_source() -> MemorySource {
+    MemorySource::new().insert(DEPOSIT_PATH, DEPOSIT_LOG)
+}
@@ -0,0 +1,36 @@
+use super::source::EventLogSource;
+use super::typ::EVENT_LOG_PATH;
+use anyhow::{anyhow, Result};
+use futures::future::{FutureExt, LocalBoxFuture};
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(module = "/output/tornado_bundle.js")]
+extern "C" {
+    pub type TornadoUtil;
+
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> TornadoUtil;
+
+    #[wasm_bindgen(method, catch)]
+    pub async fn read_file(this: &TornadoUtil, path: JsValue) -> Result<JsValue, JsValue>;
+}
+
+/// Read the cache through node `fs`, used by the wasm build.
+impl EventLogSource for TornadoUtil {
+    fn read<'a>(&'a self, path: &'a str) -> LocalBoxFuture<'a, Result<String>> {
+        async move {
+            let path = format!("{EVENT_LOG_PATH}/{path}");
+
+            Uint8Array::from(self.read_file(JsValue::from_str(&path)).await.map_err(
+                |err| anyhow!("Failed to read cache file, ensure that the file `{path}` exist.{err:?}"),
+            )?)
+            .to_string()
+            .as_string()
+            .ok_or(anyhow!(
+                "Failed to read cache file, ensure that the file format correct."
+            ))
+        }
+        .boxed_local()
+    }
+}
@@ -0,0 +1,115 @@
+use super::typ::*;
+use alloc::string::String;
+use alloc::vec::Vec;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+// keeps a manifest signature from being valid for any other message of the key
+const DOMAIN: &[u8] = b"poi blocklist manifest v1";
+
+/// What the issuer of a blocklist claims about one version of it, the root is of the
+/// `SparseMerkleTree` of its commitments.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub issuer: String,
+    pub version: u64,
+    // unix seconds
+    pub timestamp: u64,
+    pub entry_count: u64,
+    pub root: Hash,
+}
+
+impl Manifest {
+    /// The signed bytes, every field in order, the issuer prefixed by its length.
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = DOMAIN.to_vec();
+        message.extend((self.issuer.len() as u64).to_be_bytes());
+        message.extend(self.issuer.as_bytes());
+        message.extend(self.version.to_be_bytes());
+        message.extend(self.timestamp.to_be_bytes());
+        message.extend(self.entry_count.to_be_bytes());
+        message.extend(self.root);
+        message
+    }
+
+    /// blake3 hash of the signed bytes.
+    pub fn id(&self) -> Hash {
+        *blake3::hash(&self.message()).as_bytes()
+    }
+
+    /// Sign with the Ed25519 secret key of the issuer.
+    pub fn sign(self, secret_key: &Hash) -> SignedManifest {
+        let key = SigningKey::from_bytes(secret_key);
+        SignedManifest {
+            signature: key.sign(&self.message()).to_bytes().to_vec(),
+            public_key: key.verifying_key().to_bytes(),
+            manifest: self,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedManifest {
+    pub manifest: Manifest,
+    // Ed25519 key of the issuer
+    pub public_key: Hash,
+    pub signature: Vec<u8>,
+}
+
+impl SignedManifest {
+    pub fn verify(&self) -> bool {
+        let Ok(key) = VerifyingKey::from_bytes(&self.public_key) else {
+            return false;
+        };
+        Signature::from_slice(&self.signature)
+            .and_then(|signature| key.verify_strict(&self.manifest.message(), &signature))
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_manifest() {
+        let manifest = Manifest {
+            issuer: "ofac".into(),
+            version: 3,
+            timestamp: 1676880000,
+            entry_count: 2,
+            root: [1; 32],
+        };
+        let signed = manifest.clone().sign(&[7; 32]);
+        assert!(signed.verify());
+
+        let other_key = manifest.clone().sign(&[8; 32]).public_key;
+        assert!(!SignedManifest {
+            public_key: other_key,
+            ..signed.clone()
+        }
+        .verify());
+        assert!(!SignedManifest {
+            manifest: Manifest {
+                version: 4,
+                ..manifest.clone()
+            },
+            ..signed.clone()
+        }
+        .verify());
+        assert!(!SignedManifest {
+            signature: vec![],
+            ..signed
+        }
+        .verify());
+        assert_ne!(
+            manifest.id(),
+            Manifest {
+                issuer: "ofa".into(),
+                ..manifest.clone()
+            }
+            .id()
+        );
+    }
+}
@@ -1,193 +1,531 @@
-use super::Hash;
-use ff::*;
-use merkle_light::hash::Algorithm;
-use merkle_light::merkle::MerkleTree;
-use merkle_light::proof::Proof as AccuracyProof;
-use mimc_sponge_rs::{Fr, MimcSponge};
-use novasmt::{CompressedProof, Database, InMemoryCas, Tree};
+use super::mimc::mimc_hash;
+use super::{Hash, HashStr};
+use alloc::format;
+use alloc::vec::Vec;
 use num_bigint::BigUint;
 use num_traits::Num;
-use regex::Regex;
-use std::hash::Hasher;
-use std::ops::Deref;
+#[cfg(feature = "std")]
+use {
+    super::store::{FileCas, SmtStore},
+    super::FIELD_SIZE,
+    anyhow::{anyhow, Result},
+    lazy_static::lazy_static,
+    novasmt::{ContentAddrStore, Database, InMemoryCas, Tree},
+    std::ops::Deref,
+    std::path::PathBuf,
+};
 
+// a path has a sibling for every level, only building the trees needs the zero leaf
 pub const LEVEL: usize = 20;
 // keccak256("tornado") % BN254_FIELD_SIZE
+#[cfg(feature = "std")]
 pub const ZERO_ELEMENT: &str = "2fe54c60d3acabf3343a35b6eba15db4821b340f76e741e2249685ed4899af6c";
-// hash type
-const LEAF: u8 = 0x00;
-const INTERIOR: u8 = 0x01;
 
-pub struct TornadoMerkleTree(MerkleTree<Hash, MimcHasher>);
+#[cfg(feature = "std")]
+lazy_static! {
+    // ZEROS[i] is the root of an empty subtree of height i, `zeros` of the tornado contract
+    static ref ZEROS: Vec<Hash> = (0..LEVEL).fold(vec![to_hash(ZERO_ELEMENT)], |mut zeros, i| {
+        zeros.push(mimc_hash(&zeros[i], &zeros[i]));
+        zeros
+    });
+}
 
-impl Deref for TornadoMerkleTree {
-    type Target = MerkleTree<Hash, MimcHasher>;
-    fn deref(&self) -> &Self::Target {
-        &self.0
+/// Append-only tornado tree, a deposit only hashes its path to the root like the contract does.
+/// Building the trees needs std, verifying the proofs does not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TornadoMerkleTree {
+    // the non-empty nodes of every level from the leaves to the root, the last one of each level
+    // is the frontier (`filledSubtrees` of the contract), the rest is kept to prove old leaves
+    #[cfg(feature = "std")]
+    layers: Vec<Vec<Hash>>,
+}
+
+#[cfg(feature = "std")]
+impl Default for TornadoMerkleTree {
+    fn default() -> Self {
+        Self {
+            layers: vec![vec![]; LEVEL + 1],
+        }
     }
 }
 
 impl TornadoMerkleTree {
-    pub fn new(list: Vec<String>) -> Self {
-        Self(
-            MerkleTree::new(list.iter().map(|s| to_hash(s)))
-                .fixed_level(LEVEL, to_hash(ZERO_ELEMENT))
-                .build(),
-        )
+    #[cfg(feature = "std")]
+    pub fn new(list: Vec<String>) -> Result<Self> {
+        let mut tree = Self::default();
+        for leaf in list {
+            let leaf = parse_commitment(&leaf).map_err(|err| anyhow!("Leaf {err}"))?;
+            tree.append(leaf)?;
+        }
+
+        Ok(tree)
+    }
+
+    /// Returns the index of the new leaf.
+    #[cfg(feature = "std")]
+    pub fn append(&mut self, leaf: Hash) -> Result<usize> {
+        let index = self.len();
+        if index >= 1 << LEVEL {
+            return Err(anyhow!(
+                "Merkle tree is full, it has {} leaves at most",
+                1 << LEVEL
+            ));
+        }
+
+        let mut node = leaf;
+        self.layers[0].push(node);
+        for level in 0..LEVEL {
+            let i = index >> level;
+            node = match i & 1 {
+                0 => mimc_hash(&node, &ZEROS[level]),
+                _ => mimc_hash(&self.layers[level][i - 1], &node),
+            };
+
+            let parent = &mut self.layers[level + 1];
+            match parent.get_mut(i >> 1) {
+                Some(hash) => *hash = node,
+                None => parent.push(node),
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Roll back to the tree of the first `len` leaves, e.g. an older root of the contract.
+    #[cfg(feature = "std")]
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len() {
+            return;
+        }
+
+        let mut count = len;
+        for layer in &mut self.layers {
+            layer.truncate(count);
+            count = (count + 1) >> 1;
+        }
+        // only the right edge has children that are gone
+        if len > 0 {
+            for level in 0..LEVEL {
+                let i = (len - 1) >> level;
+                let left = self.layers[level][i & !1];
+                let right = self.layers[level].get(i | 1).copied();
+                self.layers[level + 1][i >> 1] = mimc_hash(&left, &right.unwrap_or(ZEROS[level]));
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub fn len(&self) -> usize {
+        self.layers[0].len()
     }
 
+    #[cfg(feature = "std")]
+    pub fn leaves(&self) -> &[Hash] {
+        &self.layers[0]
+    }
+
+    #[cfg(feature = "std")]
     pub fn root(&self) -> Hash {
-        self.0.root()
+        self.layers[LEVEL].first().copied().unwrap_or(ZEROS[LEVEL])
+    }
+
+    // the last one wins if the leaf is duplicated
+    #[cfg(feature = "std")]
+    pub fn index_of(&self, leaf: &Hash) -> Option<usize> {
+        self.layers[0].iter().rposition(|a| a == leaf)
     }
 
+    #[cfg(feature = "std")]
     pub fn prove(&self, i: usize) -> (Vec<Hash>, Vec<bool>) {
-        let proof = self.gen_proof(i);
-        (proof.lemma().to_vec(), proof.path().to_vec())
+        let mut element = vec![self.layers[0][i]];
+        let mut index = vec![];
+        for level in 0..LEVEL {
+            let j = i >> level;
+            element.push(
+                self.layers[level]
+                    .get(j ^ 1)
+                    .copied()
+                    .unwrap_or(ZEROS[level]),
+            );
+            index.push(j & 1 == 0);
+        }
+        element.push(self.root());
+
+        (element, index)
     }
 
+    /// The leaf count as 8 big-endian bytes, followed by the nodes of every level from the leaves
+    /// to the root, reloading it does not hash again.
+    #[cfg(feature = "std")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = (self.len() as u64).to_be_bytes().to_vec();
+        for node in self.layers.iter().flatten() {
+            bytes.extend(node);
+        }
+        bytes
+    }
+
+    #[cfg(feature = "std")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let err = || anyhow!("Merkle tree state is broken");
+        let (len, mut rest) = (bytes.len() >= 8)
+            .then(|| bytes.split_at(8))
+            .ok_or_else(err)?;
+        let mut len = u64::from_be_bytes(len.try_into().unwrap()) as usize;
+        if len > 1 << LEVEL {
+            return Err(err());
+        }
+
+        let mut layers = vec![];
+        for _ in 0..=LEVEL {
+            if rest.len() < len * 32 {
+                return Err(err());
+            }
+            let (layer, next) = rest.split_at(len * 32);
+            layers.push(
+                layer
+                    .chunks(32)
+                    .map(|node| node.try_into().unwrap())
+                    .collect(),
+            );
+            rest = next;
+            len = (len + 1) >> 1;
+        }
+
+        match rest.is_empty() {
+            true => Ok(Self { layers }),
+            false => Err(err()),
+        }
+    }
+
+    // `element` is [leaf, siblings.., root], `index[i]` is whether the i-th node is a left child,
+    // a shorter path would prove a subtree, or the leaf itself, as the tree
     pub fn verify(root: Hash, key: Hash, element: Vec<Hash>, index: Vec<bool>) -> bool {
-        let accuracy_proof = AccuracyProof::new(element, index);
-        let element = accuracy_proof.lemma();
+        if index.len() != LEVEL || element.len() != LEVEL + 2 {
+            return false;
+        }
+
+        let node = element[1..element.len() - 1].iter().zip(index).fold(
+            element[0],
+            |node, (sibling, is_left)| match is_left {
+                true => mimc_hash(&node, sibling),
+                false => mimc_hash(sibling, &node),
+            },
+        );
 
-        element[0] == key
-            && element[element.len() - 1] == root
-            && accuracy_proof.validate::<MimcHasher>()
+        element[0] == key && element[element.len() - 1] == root && node == root
     }
 }
 
-pub struct SparseMerkleTree(Tree<InMemoryCas>);
+pub struct SparseMerkleTree(#[cfg(feature = "std")] Tree<SmtStore>);
 
+#[cfg(feature = "std")]
 impl Deref for SparseMerkleTree {
-    type Target = Tree<InMemoryCas>;
+    type Target = Tree<SmtStore>;
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
 impl SparseMerkleTree {
+    #[cfg(feature = "std")]
     pub fn new(list: Vec<String>) -> Self {
-        let forest = Database::new(InMemoryCas::default());
-        let mut tree = forest.get_tree([0; 32]).unwrap();
+        let forest = Database::new(SmtStore::Memory(InMemoryCas::default()));
+        let mut tree = Self(forest.get_tree([0; 32]).unwrap());
 
         for item in list {
-            tree.insert(to_hash(&item), &to_hash("1"));
+            tree.insert(to_hash(&item));
+        }
+
+        tree
+    }
+
+    /// Reopen a tree of the store file by its root, the empty tree is `[0; 32]`.
+    #[cfg(feature = "std")]
+    pub fn open(path: impl Into<PathBuf>, root: Hash) -> Result<Self> {
+        let cas = FileCas::open(path)?;
+        if root != [0; 32] && cas.get(&root).is_none() {
+            return Err(anyhow!(
+                "Tree of root 0x{} is not in the store",
+                to_hash_str(&root)
+            ));
         }
+        let forest = Database::new(SmtStore::File(cas));
+
+        Ok(Self(forest.get_tree(root).unwrap()))
+    }
+
+    #[cfg(feature = "std")]
+    pub fn insert(&mut self, key: Hash) {
+        self.0.insert(key, &to_hash("1"));
+    }
 
-        Self(tree)
+    // novasmt removes a key by an empty value
+    #[cfg(feature = "std")]
+    pub fn remove(&mut self, key: Hash) {
+        self.0.insert(key, &[]);
     }
 
+    #[cfg(feature = "std")]
+    pub fn key_list(&self) -> Vec<Hash> {
+        self.iter().map(|(key, _)| key).collect()
+    }
+
+    /// Write the nodes added since the last save to the store file, a tree in memory is not
+    /// saved.
+    #[cfg(feature = "std")]
+    pub fn save(&self) -> Result<()> {
+        match self.storage() {
+            SmtStore::File(cas) => cas.flush(),
+            SmtStore::Memory(_) => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "std")]
     pub fn root(&self) -> Hash {
         self.root_hash()
     }
 
+    #[cfg(feature = "std")]
     pub fn prove(&self, key: Hash) -> Vec<u8> {
         let (_val, proof) = self.get_with_proof(key);
         proof.compress().0
     }
 
+    // non-inclusion proof of novasmt, a 256 bits bitmap of the zero siblings from the root,
+    // followed by the non-zero siblings
     pub fn verify(root: Hash, key: Hash, proof: Vec<u8>) -> bool {
-        let innocence_proof = CompressedProof(proof).decompress().unwrap();
-        innocence_proof.verify(root, key, &[])
-    }
-}
+        if proof.len() < 32 {
+            return false;
+        }
+        let (bitmap, mut rest) = proof.split_at(32);
+        let mut sibling_list = Vec::with_capacity(256);
+        for i in 0..256 {
+            if bit(bitmap, i) {
+                sibling_list.push([0; 32]);
+            } else if rest.len() >= 32 {
+                let (sibling, next) = rest.split_at(32);
+                sibling_list.push(sibling.try_into().unwrap());
+                rest = next;
+            } else {
+                return false;
+            }
+        }
 
-#[derive(Default)]
-pub struct MimcHasher {
-    inner: MimcSponge,
-    data: Vec<u8>,
-}
+        // the value hash of an absent key is zero
+        let node = sibling_list
+            .iter()
+            .enumerate()
+            .rev()
+            .fold([0; 32], |node, (i, sibling)| match bit(&key, i) {
+                true => smt_hash(sibling, &node),
+                false => smt_hash(&node, sibling),
+            });
 
-impl Deref for MimcHasher {
-    type Target = MimcSponge;
-    fn deref(&self) -> &Self::Target {
-        &self.inner
+        rest.is_empty() && node == root
     }
 }
 
-impl Hasher for MimcHasher {
-    #[inline]
-    fn write(&mut self, bytes: &[u8]) {
-        self.data.extend_from_slice(bytes);
+// novasmt node hash, blake3 keyed by the hash of "smt_node" and zero for empty subtrees
+fn smt_hash(left: &Hash, right: &Hash) -> Hash {
+    if left == &[0; 32] && right == &[0; 32] {
+        return [0; 32];
     }
 
-    #[inline]
-    fn finish(&self) -> u64 {
-        unimplemented!()
-    }
+    let mut data = [0; 64];
+    data[..32].copy_from_slice(left);
+    data[32..].copy_from_slice(right);
+    *blake3::keyed_hash(blake3::hash(b"smt_node").as_bytes(), &data).as_bytes()
 }
 
-impl Algorithm<Hash> for MimcHasher {
-    #[inline]
-    fn hash(&mut self) -> Hash {
-        match self.data[0] {
-            LEAF => (&self.data[1..=32]).try_into().unwrap(),
-            INTERIOR => {
-                let res = self.multi_hash(
-                    &[fr(&self.data[1..=32]), fr(&self.data[33..=64])],
-                    Fr::zero(),
-                    1,
-                );
-                let re = Regex::new(r"0x([0-9a-fA-F]+)").unwrap();
-                to_hash(&re.captures(&res[0].to_string()).unwrap()[1])
-            }
-            _ => unreachable!(),
-        }
-    }
+// most significant bit first
+fn bit(data: &[u8], i: usize) -> bool {
+    data[i / 8] & (0x80 >> (i % 8)) != 0
+}
 
-    #[inline]
-    fn reset(&mut self) {
-        self.data = vec![];
+/// Parse hex with or without `0x` in any case, in the BN254 scalar field like every commitment.
+#[cfg(feature = "std")]
+pub fn parse_commitment(value: &str) -> Result<Hash> {
+    let hash = try_to_hash(strip_hex_prefix(value.trim()))
+        .ok_or(anyhow!("`{value}` is not hex of 32 bytes at most"))?;
+    if BigUint::from_bytes_be(&hash) >= BigUint::from_str_radix(FIELD_SIZE, 10)? {
+        return Err(anyhow!("`{value}` is out of the BN254 scalar field"));
     }
+    Ok(hash)
 }
 
-fn fr(data: &[u8]) -> Fr {
-    Fr::from_str(&BigUint::from_bytes_be(data).to_str_radix(10)).unwrap()
-}
 pub fn to_hash(str: &str) -> Hash {
-    extend32(
-        BigUint::from_str_radix(str, 16)
-            .unwrap()
-            .to_bytes_be()
-            .as_ref(),
-    )
+    try_to_hash(str).unwrap()
+}
+pub fn to_hash_str(hash: &Hash) -> HashStr {
+    format!("{:0>64}", BigUint::from_bytes_be(hash).to_str_radix(16))
+}
+// `None` if it is not the hex of at most 32 bytes
+pub fn try_to_hash(str: &str) -> Option<Hash> {
+    let data = BigUint::from_str_radix(str, 16).ok()?.to_bytes_be();
+    (data.len() <= 32).then(|| extend32(&data))
+}
+// one `0x` or `0X` prefix
+#[cfg(feature = "std")]
+pub fn strip_hex_prefix(str: &str) -> &str {
+    str.strip_prefix("0x")
+        .or(str.strip_prefix("0X"))
+        .unwrap_or(str)
 }
 fn extend32(data: &[u8]) -> Hash {
     let len = data.len();
     if len == 32 {
         data.try_into().unwrap()
     } else if len < 32 {
-        [&vec![0; 32 - data.len()][..], &data[..]]
-            .concat()
-            .try_into()
-            .unwrap()
+        let mut hash = [0; 32];
+        hash[32 - len..].copy_from_slice(data);
+        hash
     } else {
         unreachable!()
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
-    use merkle_light::proof::Proof;
     use wasm_bindgen_test::*;
 
     const LEAF: &str = "09ee41e2a667251b7bedc2032977ab5ce9d2b2b79e158e252c13025820804dc1";
     const ROOT: &str = "29316f2a7749ea8161528e6b42cc35591d8ccddd01911028c460a7930ae00458";
 
     #[wasm_bindgen_test]
-    async fn test_tornado_merkle_tree() {
-        let t = TornadoMerkleTree::new(vec![LEAF.into()]);
+    fn test_tornado_merkle_tree() {
+        let t = TornadoMerkleTree::new(vec![LEAF.into()]).unwrap();
         assert_eq!(
             BigUint::from_bytes_be(t.root().as_ref()).to_str_radix(16),
             ROOT
         );
+        assert_eq!(
+            TornadoMerkleTree::new(vec![format!("0x{}", LEAF.to_uppercase())]).unwrap(),
+            t
+        );
+        let field_size = BigUint::from_str_radix(FIELD_SIZE, 10).unwrap();
+        for leaf in ["0xzz".into(), field_size.to_str_radix(16)] {
+            let err = TornadoMerkleTree::new(vec![leaf.clone()]).unwrap_err();
+            assert!(err.to_string().contains(&leaf));
+        }
+
+        let (element, index) = t.prove(0);
+        let key = to_hash(LEAF);
+        assert!(TornadoMerkleTree::verify(
+            t.root(),
+            key,
+            element.clone(),
+            index.clone()
+        ));
+        let mut fake_index = index.clone();
+        fake_index[0] = !fake_index[0];
+        assert!(!TornadoMerkleTree::verify(
+            t.root(),
+            key,
+            element.clone(),
+            fake_index
+        ));
+        assert!(!TornadoMerkleTree::verify(
+            t.root(),
+            key,
+            element[1..].to_vec(),
+            index.clone()
+        ));
+
+        // paths shorter than the tree prove the leaf or a subtree as the root
+        assert!(!TornadoMerkleTree::verify(key, key, vec![key, key], vec![]));
+        let subtree_root = match index[0] {
+            true => mimc_hash(&key, &element[1]),
+            false => mimc_hash(&element[1], &key),
+        };
+        assert!(!TornadoMerkleTree::verify(
+            subtree_root,
+            key,
+            vec![key, element[1], subtree_root],
+            index[..1].to_vec()
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_append() {
+        let leaves = (1..=5u8).map(|i| [i; 32]).collect::<Vec<Hash>>();
+        let mut t = TornadoMerkleTree::default();
+        let mut reloaded = TornadoMerkleTree::default();
+        for (i, leaf) in leaves.iter().enumerate() {
+            assert_eq!(t.append(*leaf).unwrap(), i);
+            reloaded = TornadoMerkleTree::from_bytes(&reloaded.to_bytes()).unwrap();
+            reloaded.append(*leaf).unwrap();
+        }
+        assert_eq!(t, reloaded);
+
+        for (i, leaf) in leaves.iter().enumerate() {
+            let (element, index) = t.prove(i);
+            assert!(TornadoMerkleTree::verify(t.root(), *leaf, element, index));
+        }
+        assert!(TornadoMerkleTree::from_bytes(&t.to_bytes()[1..]).is_err());
+
+        for len in 0..leaves.len() {
+            let mut old = t.clone();
+            let mut expected = TornadoMerkleTree::default();
+            old.truncate(len);
+            for leaf in &leaves[..len] {
+                expected.append(*leaf).unwrap();
+            }
+            assert_eq!(old, expected);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_sparse_merkle_tree() {
+        let t = SparseMerkleTree::new(vec![LEAF.into(), ZERO_ELEMENT.into()]);
+        let key = to_hash(ROOT);
+        let proof = t.prove(key);
+        assert!(novasmt::CompressedProof(proof.clone())
+            .decompress()
+            .unwrap()
+            .verify(t.root(), key, &[]));
+        assert!(SparseMerkleTree::verify(t.root(), key, proof.clone()));
+        assert!(!SparseMerkleTree::verify(
+            t.root(),
+            key,
+            proof[1..].to_vec()
+        ));
+
+        let key = to_hash(LEAF);
+        assert!(!SparseMerkleTree::verify(t.root(), key, t.prove(key)));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_sparse_merkle_tree_store() {
+        let path = std::env::temp_dir().join(format!("poi_smt_{}.store", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let (leaf, zero) = (to_hash(LEAF), to_hash(ZERO_ELEMENT));
+
+        let mut t = SparseMerkleTree::open(&path, [0; 32]).unwrap();
+        t.insert(leaf);
+        t.insert(zero);
+        t.save().unwrap();
+        let full_root = t.root();
+        assert_eq!(
+            full_root,
+            SparseMerkleTree::new(vec![LEAF.into(), ZERO_ELEMENT.into()]).root()
+        );
 
-        let proof = t.gen_proof(0);
-        assert!(proof.validate::<MimcHasher>());
+        let mut t = SparseMerkleTree::open(&path, full_root).unwrap();
+        assert_eq!(t.key_list().len(), 2);
+        t.remove(zero);
+        t.save().unwrap();
+        assert_eq!(t.root(), SparseMerkleTree::new(vec![LEAF.into()]).root());
 
-        let mut fake_path = proof.path().to_vec();
-        fake_path[0] = !fake_path[0];
-        let fake_proof = Proof::new(proof.lemma().to_vec(), fake_path);
-        assert!(fake_proof.validate::<MimcHasher>() == false);
+        // every saved root can be reopened
+        let t = SparseMerkleTree::open(&path, full_root).unwrap();
+        assert!(SparseMerkleTree::verify(
+            full_root,
+            to_hash(ROOT),
+            t.prove(to_hash(ROOT))
+        ));
+        assert!(SparseMerkleTree::open(&path, to_hash(ROOT)).is_err());
+        std::fs::remove_file(&path).unwrap();
     }
 }
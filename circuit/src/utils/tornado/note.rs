@@ -1,22 +1,33 @@
+use super::pedersen::pedersen_hash;
+use super::source::EventLogSource;
 use super::typ::*;
 use anyhow::{anyhow, Result};
-use js_sys::Uint8Array;
 use num_bigint::BigUint;
 use num_traits::Num;
 use regex::Regex;
-use wasm_bindgen::JsValue;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Default, Debug, PartialEq, Eq, Clone, Hash)]
 pub struct Note {
-    currency: String,
-    amount: String,
-    net_id: u32,
+    pool: Pool,
     nullifier_hash: HashStr,
     commitment_hash: HashStr,
 }
 
+/// The tornado instance a note is deposited to, notes of the same pool share one deposit tree.
+#[derive(Default, Debug, PartialEq, Eq, Clone, Hash, Serialize)]
+pub struct Pool {
+    pub currency: String,
+    pub amount: String,
+    pub net_id: u32,
+}
+
 impl Note {
-    pub fn new(note: &str, util: &TornadoUtil) -> Result<Self> {
+    pub fn new(note: &str) -> Result<Self> {
         let re = Regex::new(NOTE_REGEX)?;
         let caps = re
             .captures(note)
@@ -25,67 +36,108 @@ impl Note {
         let currency = caps.name("currency").unwrap().as_str().into();
         let amount = caps.name("amount").unwrap().as_str().into();
         let net_id = caps.name("netId").unwrap().as_str().parse()?;
-        let note = BigUint::from_str_radix(caps.name("note").unwrap().as_str(), 16)
-            .unwrap()
-            .to_bytes_be();
-        let preimage = Uint8Array::from(&note[..]);
-        let nullifier = Uint8Array::from(&note[..31]);
-        let commitment_hash = hash_data(preimage, util)?;
-        let nullifier_hash = hash_data(nullifier, util)?;
+        let note = decode_preimage(caps.name("note").unwrap().as_str());
+        let commitment_hash = pedersen_hash(&note)?;
+        let nullifier_hash = pedersen_hash(&note[..NULLIFIER_LEN])?;
 
         Ok(Self {
-            currency,
-            amount,
-            net_id,
+            pool: Pool {
+                currency,
+                amount,
+                net_id,
+            },
             commitment_hash,
             nullifier_hash,
         })
     }
 
+    /// The secret of a note, nullifier followed by secret, only the zkVM guest should see it.
+    pub fn preimage(note: &str) -> Result<Vec<u8>> {
+        let re = Regex::new(NOTE_REGEX)?;
+        let caps = re
+            .captures(note)
+            .ok_or(anyhow!("Tornado note `{note}` format is incorrect"))?;
+
+        Ok(decode_preimage(caps.name("note").unwrap().as_str()))
+    }
+
     pub async fn read_event_log(
         &self,
         typ: Option<EventLogType>,
-        util: &TornadoUtil,
+        source: &impl EventLogSource,
     ) -> Result<Vec<EventLog>> {
-        let net_id = self.net_id;
-        let base_dir = &format!(
-            "{}/{}",
-            EVENT_LOG_PATH,
-            NET_NAME_MAP
-                .get(&net_id)
-                .ok_or(anyhow!("Net#{net_id} not support"))?
-        );
+        self.pool.read_event_log(typ, source).await
+    }
+
+    pub fn commitment(&self) -> &HashStr {
+        &self.commitment_hash
+    }
+
+    pub fn nullifier_hash(&self) -> &HashStr {
+        &self.nullifier_hash
+    }
+
+    pub fn pool(&self) -> &Pool {
+        &self.pool
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.pool.currency
+    }
+
+    pub fn amount(&self) -> &str {
+        &self.pool.amount
+    }
+
+    pub fn net_id(&self) -> u32 {
+        self.pool.net_id
+    }
+}
+
+impl Pool {
+    pub async fn read_event_log(
+        &self,
+        typ: Option<EventLogType>,
+        source: &impl EventLogSource,
+    ) -> Result<Vec<EventLog>> {
+        let deposit_list = || async {
+            let log_list = self.read_deposit_log(source).await?;
+            Ok::<_, anyhow::Error>(log_list.into_iter().map(EventLog::Deposit).collect())
+        };
+        let withdraw_list = || async {
+            let log_list = self.read_withdraw_log(source).await?;
+            Ok::<_, anyhow::Error>(log_list.into_iter().map(EventLog::Withdraw).collect())
+        };
 
         match typ {
-            Some(typ @ (EventLogType::Deposit | EventLogType::Withdrawal)) => {
-                let content = self.read_file(util, base_dir, typ).await?;
-                Ok(serde_json::from_str(&content)?)
-            }
-            _ => {
-                let content = self
-                    .read_file(util, base_dir, EventLogType::Deposit)
-                    .await?;
-                let deposit_list: Vec<EventLog> = serde_json::from_str(&content)?;
-                let content = self
-                    .read_file(util, base_dir, EventLogType::Withdrawal)
-                    .await?;
-                let withdraw_list: Vec<EventLog> = serde_json::from_str(&content)?;
-                Ok([deposit_list, withdraw_list].concat())
-            }
+            Some(EventLogType::Deposit) => deposit_list().await,
+            Some(EventLogType::Withdrawal) => withdraw_list().await,
+            None => Ok([deposit_list().await?, withdraw_list().await?].concat()),
         }
     }
 
-    pub fn commitment(&self) -> &HashStr {
-        &self.commitment_hash
+    pub async fn read_deposit_log(&self, source: &impl EventLogSource) -> Result<Vec<DepositLog>> {
+        self.read_file(source, EventLogType::Deposit).await
+    }
+
+    pub async fn read_withdraw_log(
+        &self,
+        source: &impl EventLogSource,
+    ) -> Result<Vec<WithdrawLog>> {
+        self.read_file(source, EventLogType::Withdrawal).await
     }
 
-    async fn read_file(
+    // every entry is parsed and checked on its own, so errors point at its index in the cache
+    async fn read_file<T: DeserializeOwned + LogEntry>(
         &self,
-        util: &TornadoUtil,
-        base_dir: &str,
+        source: &impl EventLogSource,
         typ: EventLogType,
-    ) -> Result<String> {
-        // tornado event log cache file path, only cache data used for convenience
+    ) -> Result<Vec<T>> {
+        let net_id = self.net_id;
+        let base_dir = *NET_NAME_MAP
+            .get(&net_id)
+            .ok_or(anyhow!("Net#{net_id} not support"))?;
+        // tornado event log cache file path, relative to the cache root of source
         let path = format!(
             "{}/{}_{}_{}.json",
             base_dir,
@@ -94,65 +146,125 @@ impl Note {
             self.amount
         );
 
-        Uint8Array::from(
-            util.read_file(JsValue::from_str(&path))
-                .await
-                .map_err(|err| {
-                    anyhow!("Failed to read cache file, ensure that the file `{path}` exit.{err:?}")
-                })?,
+        let content = source.read(&path).await?;
+        let entry_list: Vec<Value> = serde_json::from_str(&content)
+            .map_err(|err| anyhow!("Cache `{path}` should be a json array.{err}"))?;
+        entry_list
+            .into_iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                serde_json::from_value::<T>(entry)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|log| log.check().map(|_| log))
+                    .map_err(|err| anyhow!("Entry #{i} of cache `{path}` is invalid: {err}"))
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} on net#{}",
+            self.amount, self.currency, self.net_id
         )
-        .to_string()
-        .as_string()
-        .ok_or(anyhow!(
-            "Failed to read cache file, ensure that the file format correct."
-        ))
     }
 }
 
-fn hash_data(data: Uint8Array, util: &TornadoUtil) -> Result<String> {
-    Ok(format!(
-        "{:0>64}",
-        util.pedersen_hash(data)
-            .to_string(16)
-            .map_err(|err| anyhow!("String hash error: {err:?}"))?
-    ))
+// `<currency>-<amount>-<netId>`, the pool part of a note
+impl FromStr for Pool {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut part = s.splitn(3, '-');
+        match (part.next(), part.next(), part.next().map(str::parse)) {
+            (Some(currency), Some(amount), Some(Ok(net_id))) => Ok(Self {
+                currency: currency.into(),
+                amount: amount.into(),
+                net_id,
+            }),
+            _ => Err(anyhow!("Pool `{s}` should be like `eth-0.1-1`")),
+        }
+    }
+}
+
+// the regex guarantees an even number of hex digits
+fn decode_preimage(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+trait LogEntry {
+    fn check(&self) -> Result<()>;
+}
+
+impl LogEntry for DepositLog {
+    fn check(&self) -> Result<()> {
+        check_hex("transactionHash", &self.transaction_hash, 32)?;
+        if let Some(from) = &self.from {
+            check_hex("from", from, 20)?;
+        }
+        check_field("commitment", &self.commitment)
+    }
+}
+
+impl LogEntry for WithdrawLog {
+    fn check(&self) -> Result<()> {
+        check_hex("transactionHash", &self.transaction_hash, 32)?;
+        check_hex("to", &self.to, 20)?;
+        check_field("nullifierHash", &self.nullifier_hash)
+    }
+}
+
+// `0x` followed by `len` bytes of hex
+fn check_hex(name: &str, value: &str, len: usize) -> Result<()> {
+    match value.strip_prefix("0x") {
+        Some(hex) if hex.len() == len * 2 && hex.bytes().all(|b| b.is_ascii_hexdigit()) => Ok(()),
+        _ => Err(anyhow!(
+            "`{name}` should be {len} bytes of 0x hex, got `{value}`"
+        )),
+    }
+}
+
+// commitments and nullifier hashes are BN254 scalar field elements
+fn check_field(name: &str, value: &str) -> Result<()> {
+    check_hex(name, value, 32)?;
+    if BigUint::from_str_radix(&value[2..], 16)? >= BigUint::from_str_radix(FIELD_SIZE, 10)? {
+        return Err(anyhow!("`{name}` {value} is out of the BN254 scalar field"));
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::tornado::Tornado;
+    use crate::utils::tornado::fixture::*;
+    use crate::utils::tornado::MemorySource;
+    #[cfg(feature = "wasm")]
+    use crate::utils::tornado::TornadoUtil;
     use wasm_bindgen_test::*;
 
     const NET_ID: u32 = 5;
     const CURRENCY: &str = "eth";
     const AMOUNT: &str = "0.1";
 
-    // https://goerli.etherscan.io/tx/0x06e10a9ea49183e9127fb7581d4d54750290c1ecc7c7f1707953f706fe9ab959
-    const NOTE: &str = r"tornado-eth-0.1-5-0xebcf5edb762e52e6eb0f33818c647cdceb75d1cd6609847ec56b750445de0b659a11796781c60aaf3ba5d693b360a77d5cff360c982ed9dc2fd419b858d3";
-    const NULLIFIER_HASH: &str = "2d39004125a3df2cbb59ad3aa3dee045fac6f176376343632be7b9cc476ad423";
-    const COMMITMENT_HASH: &str =
-        "296137799075f986ce4c0bbbfdade2b96689d7720d2e8b59a84bf48f4afe9ad8";
-
-    // https://goerli.etherscan.io/tx/0x7b5ee6c14b86509c2b401ee1ec15657f303494acfe5d786cc4081a6666f34414
-    const COST_NOTE: &str = r"tornado-eth-0.1-5-0x4805479a68a261e0850509d4a0724877c9395be42d78146b05880d7fd4b9484e92c8de0dfc2df89aae1a7d87726da32eed131fde50bff26a0392ce2b6729";
-    const COST_NULLIFIER_HASH: &str =
-        "29e06ac32f5db0048ed954c971413c676bdf65bc318ee72bc52ce6162c76bf09";
-    const COST_COMMITMENT_HASH: &str =
-        "0129af81b9bdf54d834cdef1c6aab21c5ff95e4c40f10bc3a013bd929fbc38ac";
-
     #[wasm_bindgen_test]
-    async fn test_parse_note() {
-        let tornado = Tornado::new(vec![], vec![]).await.unwrap();
-        let note = Note::new(NOTE, &tornado.util).unwrap();
-        let cost_note = Note::new(COST_NOTE, &tornado.util).unwrap();
+    fn test_parse_note() {
+        let note = Note::new(NOTE).unwrap();
+        let cost_note = Note::new(COST_NOTE).unwrap();
 
         assert_eq!(
             note,
             Note {
-                currency: CURRENCY.into(),
-                amount: AMOUNT.into(),
-                net_id: NET_ID,
+                pool: Pool {
+                    currency: CURRENCY.into(),
+                    amount: AMOUNT.into(),
+                    net_id: NET_ID,
+                },
                 nullifier_hash: NULLIFIER_HASH.into(),
                 commitment_hash: COMMITMENT_HASH.into(),
             }
@@ -160,27 +272,65 @@ mod tests {
         assert_eq!(
             cost_note,
             Note {
-                currency: CURRENCY.into(),
-                amount: AMOUNT.into(),
-                net_id: NET_ID,
+                pool: Pool {
+                    currency: CURRENCY.into(),
+                    amount: AMOUNT.into(),
+                    net_id: NET_ID,
+                },
                 nullifier_hash: COST_NULLIFIER_HASH.into(),
                 commitment_hash: COST_COMMITMENT_HASH.into(),
             }
         );
+        assert_eq!(note.pool(), cost_note.pool());
+        assert_eq!(note.pool().to_string(), "0.1 eth on net#5");
+        assert_eq!(&"eth-0.1-5".parse::<Pool>().unwrap(), note.pool());
+        assert!("eth-0.1".parse::<Pool>().is_err());
     }
 
+    #[cfg(feature = "wasm")]
     #[wasm_bindgen_test]
     async fn test_read_event_log() {
-        let tornado = Tornado::new(vec![], vec![]).await.unwrap();
-        Note::new(NOTE, &tornado.util)
+        let util = TornadoUtil::new();
+        Note::new(NOTE)
             .unwrap()
-            .read_event_log(Some(EventLogType::Deposit), &tornado.util)
+            .read_event_log(Some(EventLogType::Deposit), &util)
             .await
             .unwrap();
-        Note::new(NOTE, &tornado.util)
+        Note::new(NOTE)
             .unwrap()
-            .read_event_log(Some(EventLogType::Deposit), &tornado.util)
+            .read_event_log(Some(EventLogType::Deposit), &util)
             .await
             .unwrap();
     }
+
+    #[wasm_bindgen_test]
+    async fn test_read_event_log_from_memory() {
+        let note = Note::new(NOTE).unwrap();
+        let source = deposit_source().insert(WITHDRAW_PATH, "[]");
+
+        let log_list = note.read_event_log(None, &source).await.unwrap();
+        assert_eq!(log_list.len(), 2);
+        assert!(note.read_event_log(None, &deposit_source()).await.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_read_invalid_event_log() {
+        let note = Note::new(NOTE).unwrap();
+        let pool = note.pool();
+        let read = |content: &str| {
+            let source = MemorySource::new().insert(DEPOSIT_PATH, content);
+            async move { pool.read_deposit_log(&source).await }
+        };
+        let withdraw = r#"{"blockNumber":8525400,"transactionHash":"0x7b5ee6c14b86509c2b401ee1ec15657f303494acfe5d786cc4081a6666f34414","nullifierHash":"0x2d39004125a3df2cbb59ad3aa3dee045fac6f176376343632be7b9cc476ad423","to":"0x0000000000000000000000000000000000000000","fee":"0"}"#;
+        let out_of_field = DEPOSIT_LOG.replace(COMMITMENT_HASH, &"f".repeat(64));
+
+        assert_eq!(read(DEPOSIT_LOG).await.unwrap()[0].leaf_index, 0);
+        // between the two deposits
+        let err = read(&DEPOSIT_LOG.replacen("},{", &format!("}},{withdraw},{{"), 1))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Entry #1 of cache"));
+        assert!(read(&out_of_field).await.is_err());
+        assert!(read("{}").await.is_err());
+    }
 }
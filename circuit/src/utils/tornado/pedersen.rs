@@ -0,0 +1,150 @@
+use super::typ::{HashStr, FIELD_SIZE};
+use alloc::format;
+use alloc::vec::Vec;
+use anyhow::{anyhow, Result};
+use num_bigint::{BigInt, BigUint};
+use num_traits::{Num, One, Signed, Zero};
+
+// baby jubjub, twisted edwards curve over the BN254 scalar field (EIP-2494)
+const SUB_ORDER: &str =
+    "2736030358979909402780800718157159386076813972158567259200215660948447373041";
+const A: u32 = 168700;
+const D: u32 = 168696;
+// circomlib pedersen generators, each one covers a 200 bits segment
+const BASE_POINT: [[&str; 2]; 3] = [
+    [
+        "10457101036533406547632367118273992217979173478358440826365724437999023779287",
+        "19824078218392094440610104313265183977899662750282163392862422243483260492317",
+    ],
+    [
+        "2671756056509184035029146175565761955751135805354291559563293617232983272177",
+        "2663205510731142763556352975002641716101654201788071096152948830924149045094",
+    ],
+    [
+        "5802099305472655231388284418920769829666717045250560929368476121199858275951",
+        "5980429700218124965372158798884772646841287887664001482443826541541529227896",
+    ],
+];
+const WINDOW_SIZE: usize = 4;
+const WINDOWS_PER_SEGMENT: usize = 50;
+const BITS_PER_SEGMENT: usize = WINDOW_SIZE * WINDOWS_PER_SEGMENT;
+
+/// Pedersen hash compatible with circomlibjs, returns the x coordinate of the result point.
+pub fn pedersen_hash(data: &[u8]) -> Result<HashStr> {
+    let bits = data
+        .iter()
+        .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+        .collect::<Vec<bool>>();
+    if bits.is_empty() || bits.len() > BASE_POINT.len() * BITS_PER_SEGMENT {
+        return Err(anyhow!(
+            "Pedersen hash only supports 1 to {} bytes of data",
+            BASE_POINT.len() * BITS_PER_SEGMENT / 8
+        ));
+    }
+
+    let p = &parse(FIELD_SIZE);
+    let l = &BigInt::from(parse(SUB_ORDER));
+    let mut acc = Point::identity();
+    for (segment, [x, y]) in bits.chunks(BITS_PER_SEGMENT).zip(BASE_POINT) {
+        let base = Point::new(parse(x), parse(y));
+        let mut scalar = BigInt::zero();
+        let mut exp = BigInt::one();
+
+        for window in segment.chunks(WINDOW_SIZE) {
+            let mut value = BigInt::one();
+            for (i, bit) in window.iter().take(WINDOW_SIZE - 1).enumerate() {
+                if *bit {
+                    value += BigInt::one() << i;
+                }
+            }
+            if window.len() == WINDOW_SIZE && window[WINDOW_SIZE - 1] {
+                value = -value;
+            }
+            scalar += value * &exp;
+            exp <<= WINDOW_SIZE + 1;
+        }
+        if scalar.is_negative() {
+            scalar += l;
+        }
+
+        acc = acc.add(&base.mul(&scalar.to_biguint().unwrap(), p), p);
+    }
+
+    Ok(format!("{:0>64}", acc.affine_x(p).to_str_radix(16)))
+}
+
+fn parse(decimal: &str) -> BigUint {
+    BigUint::from_str_radix(decimal, 10).unwrap()
+}
+
+// projective coordinates, avoid the field inversion on every addition
+#[derive(Clone)]
+struct Point {
+    x: BigUint,
+    y: BigUint,
+    z: BigUint,
+}
+
+impl Point {
+    fn new(x: BigUint, y: BigUint) -> Self {
+        Self {
+            x,
+            y,
+            z: BigUint::one(),
+        }
+    }
+
+    fn identity() -> Self {
+        Self::new(BigUint::zero(), BigUint::one())
+    }
+
+    // https://hyperelliptic.org/EFD/g1p/auto-twisted-projective.html#addition-add-2008-bbjlp
+    fn add(&self, other: &Self, p: &BigUint) -> Self {
+        let a = (&self.z * &other.z) % p;
+        let b = (&a * &a) % p;
+        let c = (&self.x * &other.x) % p;
+        let d = (&self.y * &other.y) % p;
+        let e = (BigUint::from(D) * &c * &d) % p;
+        let f = (&b + p - &e) % p;
+        let g = (&b + &e) % p;
+        let h = ((&self.x + &self.y) * (&other.x + &other.y)) % p;
+
+        Self {
+            x: (&a * &f % p) * ((h + p + p - &c - &d) % p) % p,
+            y: (&a * &g % p) * ((&d + p - BigUint::from(A) * &c % p) % p) % p,
+            z: (f * g) % p,
+        }
+    }
+
+    fn mul(&self, scalar: &BigUint, p: &BigUint) -> Self {
+        let mut res = Self::identity();
+        for i in (0..scalar.bits()).rev() {
+            res = res.add(&res, p);
+            if scalar.bit(i) {
+                res = res.add(self, p);
+            }
+        }
+        res
+    }
+
+    fn affine_x(&self, p: &BigUint) -> BigUint {
+        (&self.x * self.z.modpow(&(p - 2u32), p)) % p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    const DATA: &str = "ebcf5edb762e52e6eb0f33818c647cdceb75d1cd6609847ec56b750445de0b659a11796781c60aaf3ba5d693b360a77d5cff360c982ed9dc2fd419b858d3";
+    const HASH: &str = "296137799075f986ce4c0bbbfdade2b96689d7720d2e8b59a84bf48f4afe9ad8";
+
+    #[wasm_bindgen_test]
+    fn test_pedersen_hash() {
+        let data = BigUint::from_str_radix(DATA, 16).unwrap().to_bytes_be();
+        assert_eq!(pedersen_hash(&data).unwrap(), HASH);
+        assert!(pedersen_hash(&[]).is_err());
+        assert!(pedersen_hash(&[0; 76]).is_err());
+    }
+}
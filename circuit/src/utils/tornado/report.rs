@@ -0,0 +1,144 @@
+use super::merkle::try_to_hash;
+use super::note::{Note, Pool};
+use super::source::EventLogSource;
+use super::typ::*;
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt;
+
+/// Where the funds of a note went, what a compliance officer asks about a deposit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NoteReport {
+    pub pool: Pool,
+    pub commitment: HashStr,
+    pub nullifier_hash: HashStr,
+    // `None` if the deposit is not in the cache
+    pub deposit: Option<DepositLog>,
+    // `None` if the note is unspent
+    pub withdrawal: Option<WithdrawLog>,
+    // blocks and seconds from the deposit to the withdrawal, the seconds need the timestamp of
+    // the withdrawal which the tornado-cli cache does not keep
+    pub block_delay: Option<u32>,
+    pub time_delay: Option<u64>,
+}
+
+impl Note {
+    pub async fn report(&self, source: &impl EventLogSource) -> Result<NoteReport> {
+        let commitment = try_to_hash(self.commitment());
+        let nullifier_hash = try_to_hash(self.nullifier_hash());
+        let mut deposit = None;
+        let mut withdrawal = None;
+
+        for log in self.read_event_log(None, source).await? {
+            match log {
+                EventLog::Deposit(log)
+                    if try_to_hash(log.commitment.trim_start_matches("0x")) == commitment =>
+                {
+                    deposit = Some(log)
+                }
+                EventLog::Withdraw(log)
+                    if try_to_hash(log.nullifier_hash.trim_start_matches("0x"))
+                        == nullifier_hash =>
+                {
+                    withdrawal = Some(log)
+                }
+                _ => {}
+            }
+        }
+
+        let (block_delay, time_delay) = match (&deposit, &withdrawal) {
+            (Some(deposit), Some(withdrawal)) => (
+                withdrawal.block_number.checked_sub(deposit.block_number),
+                deposit.timestamp.parse::<u64>().ok().and_then(|deposit| {
+                    let withdrawal = withdrawal.timestamp.as_ref()?.parse::<u64>().ok()?;
+                    withdrawal.checked_sub(deposit)
+                }),
+            ),
+            _ => (None, None),
+        };
+
+        Ok(NoteReport {
+            pool: self.pool().clone(),
+            commitment: self.commitment().clone(),
+            nullifier_hash: self.nullifier_hash().clone(),
+            deposit,
+            withdrawal,
+            block_delay,
+            time_delay,
+        })
+    }
+}
+
+impl fmt::Display for NoteReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "pool:            {}", self.pool)?;
+        writeln!(f, "commitment:      0x{}", self.commitment)?;
+        writeln!(f, "nullifier hash:  0x{}", self.nullifier_hash)?;
+
+        match &self.deposit {
+            Some(log) => {
+                writeln!(f, "deposit")?;
+                writeln!(f, "  block:         {}", log.block_number)?;
+                writeln!(f, "  timestamp:     {}", log.timestamp)?;
+                writeln!(f, "  transaction:   {}", log.transaction_hash)?;
+                if let Some(from) = &log.from {
+                    writeln!(f, "  from:          {from}")?;
+                }
+                writeln!(f, "  leaf index:    {}", log.leaf_index)?;
+            }
+            None => writeln!(f, "deposit:         not found in cache")?,
+        }
+
+        match &self.withdrawal {
+            Some(log) => {
+                writeln!(f, "withdrawal")?;
+                writeln!(f, "  block:         {}", log.block_number)?;
+                if let Some(timestamp) = &log.timestamp {
+                    writeln!(f, "  timestamp:     {timestamp}")?;
+                }
+                writeln!(f, "  transaction:   {}", log.transaction_hash)?;
+                writeln!(f, "  to:            {}", log.to)?;
+                writeln!(f, "  fee:           {}", log.fee)?;
+            }
+            None => writeln!(f, "withdrawal:      unspent")?,
+        }
+
+        if let Some(block_delay) = self.block_delay {
+            write!(f, "delay:           {block_delay} blocks")?;
+            if let Some(time_delay) = self.time_delay {
+                write!(f, ", {time_delay} seconds")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tornado::fixture::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    async fn test_report() {
+        let note = Note::new(NOTE).unwrap();
+        let source = deposit_source();
+
+        let report = note
+            .report(&source.clone().insert(WITHDRAW_PATH, "[]"))
+            .await
+            .unwrap();
+        assert_eq!(report.deposit.unwrap().leaf_index, 0);
+        assert_eq!(report.withdrawal, None);
+        assert_eq!(report.block_delay, None);
+
+        let report = note
+            .report(&source.insert(WITHDRAW_PATH, WITHDRAW_LOG))
+            .await
+            .unwrap();
+        assert_eq!(report.block_delay, Some(111));
+        assert_eq!(report.time_delay, Some(1332));
+        assert!(report.to_string().contains("111 blocks, 1332 seconds"));
+    }
+}
@@ -0,0 +1,173 @@
+use super::merkle::{parse_commitment, strip_hex_prefix, to_hash_str, try_to_hash};
+use super::typ::*;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::fmt;
+
+/// What a blocklist entry is found to be in the deposit logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum EntryKind {
+    /// sender of deposits
+    Address,
+    /// deposit transaction hash
+    Transaction,
+    Commitment,
+}
+
+/// The commitments a blocklist entry stands for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Resolution {
+    // the named blocklist of the entry, `None` for the blocklist of `Tornado::new`
+    pub list: Option<String>,
+    pub entry: String,
+    // `None` if no deposit matches, the entry is still blocked as a commitment unless it is an
+    // address or out of the BN254 scalar field
+    pub kind: Option<EntryKind>,
+    pub commitment: Vec<HashStr>,
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(list) = &self.list {
+            write!(f, "[{list}] ")?;
+        }
+        match self.kind {
+            Some(kind) => write!(
+                f,
+                "0x{}: {kind:?} of {} deposits",
+                self.entry,
+                self.commitment.len()
+            ),
+            None => write!(f, "0x{}: no deposit in the cache", self.entry),
+        }
+    }
+}
+
+/// Resolve the blocklist to commitments through the deposits, an entry of 20 bytes is an address,
+/// any other one a transaction hash or a commitment.
+pub fn resolve_block_list<'a>(
+    block_list: &[HashStr],
+    deposit_list: impl Iterator<Item = &'a DepositLog> + Clone,
+) -> Result<Vec<Resolution>> {
+    block_list
+        .iter()
+        .map(|entry| {
+            let entry = strip_hex_prefix(entry).to_lowercase();
+            let hash = try_to_hash(&entry).ok_or(anyhow!(
+                "Blocklist entry `{entry}` is not an address, transaction hash or commitment."
+            ))?;
+            let commitment_of = |matched: &dyn Fn(&DepositLog) -> bool| {
+                deposit_list
+                    .clone()
+                    .filter(|log| matched(log))
+                    .map(|log| to_hash_str(&log_hash(&log.commitment)))
+                    .collect::<Vec<_>>()
+            };
+
+            let (kind, commitment) = if entry.len() == 40 {
+                let commitment = commitment_of(&|log| {
+                    log.from.as_ref().is_some_and(|from| {
+                        from.trim_start_matches("0x").eq_ignore_ascii_case(&entry)
+                    })
+                });
+                (Some(EntryKind::Address), commitment)
+            } else {
+                let commitment = commitment_of(&|log| log_hash(&log.transaction_hash) == hash);
+                match commitment.is_empty() {
+                    false => (Some(EntryKind::Transaction), commitment),
+                    true => {
+                        let found = deposit_list
+                            .clone()
+                            .any(|log| log_hash(&log.commitment) == hash);
+                        // e.g. the transaction hash of a deposit of another pool
+                        let commitment = match parse_commitment(&entry) {
+                            Ok(hash) => vec![to_hash_str(&hash)],
+                            Err(_) => vec![],
+                        };
+                        (found.then_some(EntryKind::Commitment), commitment)
+                    }
+                }
+            };
+
+            Ok(Resolution {
+                list: None,
+                kind: kind.filter(|_| !commitment.is_empty()),
+                entry,
+                commitment,
+            })
+        })
+        .collect()
+}
+
+// the logs are checked to be 0x hex when read
+fn log_hash(hex: &str) -> Hash {
+    try_to_hash(hex.trim_start_matches("0x")).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tornado::to_hash;
+    use wasm_bindgen_test::*;
+
+    const FROM: &str = "0x8a2aa7e8b3b4ce1ee2a1a0b9b1b0f1ed0f0d8e9c";
+
+    fn log(transaction_hash: &str, commitment: &str, from: Option<&str>) -> DepositLog {
+        DepositLog {
+            transaction_hash: transaction_hash.into(),
+            commitment: commitment.into(),
+            from: from.map(Into::into),
+            ..Default::default()
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resolve_block_list() {
+        let deposit_list = [
+            log("0x0a", "0x01", Some(FROM)),
+            log("0x0b", "0x02", Some(FROM)),
+            log("0x0c", "0x03", None),
+        ];
+        let block_list = vec![
+            FROM.to_uppercase().replace("0X", "0x"),
+            "0x0C".into(),
+            "02".into(),
+            "04".into(),
+            "0000000000000000000000000000000000000001".into(),
+            // a transaction hash out of the field
+            "0x7b5ee6c14b86509c2b401ee1ec15657f303494acfe5d786cc4081a6666f34414".into(),
+        ];
+        let resolution = resolve_block_list(&block_list, deposit_list.iter()).unwrap();
+        let kind = resolution.iter().map(|r| r.kind).collect::<Vec<_>>();
+        let commitment = |i: usize| -> Vec<Hash> {
+            resolution[i]
+                .commitment
+                .iter()
+                .map(|hash| try_to_hash(hash).unwrap())
+                .collect()
+        };
+
+        assert_eq!(
+            kind,
+            vec![
+                Some(EntryKind::Address),
+                Some(EntryKind::Transaction),
+                Some(EntryKind::Commitment),
+                None,
+                None,
+                None,
+            ]
+        );
+        assert_eq!(commitment(0), vec![to_hash("01"), to_hash("02")]);
+        assert_eq!(commitment(1), vec![to_hash("03")]);
+        // an unmatched hash is still blocked, an unmatched address is not
+        assert_eq!(commitment(3), vec![to_hash("04")]);
+        assert!(commitment(4).is_empty());
+        assert!(commitment(5).is_empty());
+        let deposit_list = [log(&block_list[5], "0x05", None)];
+        let resolution = resolve_block_list(&block_list[5..], deposit_list.iter()).unwrap();
+        assert_eq!(resolution[0].kind, Some(EntryKind::Transaction));
+        assert_eq!(resolution[0].commitment, vec![to_hash_str(&to_hash("05"))]);
+        assert!(resolve_block_list(&["0xzz".into()], deposit_list.iter()).is_err());
+    }
+}
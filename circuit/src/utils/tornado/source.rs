@@ -0,0 +1,72 @@
+use super::typ::*;
+use anyhow::{anyhow, Result};
+use futures::future::{FutureExt, LocalBoxFuture};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Where the tornado event log cache is read from, `path` is relative to the cache root,
+/// e.g. `goerli/deposits_eth_0.1.json`.
+pub trait EventLogSource {
+    fn read<'a>(&'a self, path: &'a str) -> LocalBoxFuture<'a, Result<String>>;
+}
+
+/// Read the cache from the local filesystem.
+pub struct FsSource {
+    base_dir: PathBuf,
+}
+
+impl FsSource {
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+        }
+    }
+}
+
+impl Default for FsSource {
+    fn default() -> Self {
+        Self::new(EVENT_LOG_PATH)
+    }
+}
+
+impl EventLogSource for FsSource {
+    fn read<'a>(&'a self, path: &'a str) -> LocalBoxFuture<'a, Result<String>> {
+        async move {
+            let path = self.base_dir.join(path);
+            std::fs::read_to_string(&path).map_err(|err| {
+                anyhow!(
+                    "Failed to read cache file, ensure that the file `{}` exist.{err}",
+                    path.display()
+                )
+            })
+        }
+        .boxed_local()
+    }
+}
+
+/// Keep the cache in memory, mainly for tests.
+#[derive(Clone, Default)]
+pub struct MemorySource(HashMap<String, String>);
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(mut self, path: &str, content: &str) -> Self {
+        self.0.insert(path.into(), content.into());
+        self
+    }
+}
+
+impl EventLogSource for MemorySource {
+    fn read<'a>(&'a self, path: &'a str) -> LocalBoxFuture<'a, Result<String>> {
+        async move {
+            self.0
+                .get(path)
+                .cloned()
+                .ok_or(anyhow!("Cache `{path}` not exist in memory"))
+        }
+        .boxed_local()
+    }
+}
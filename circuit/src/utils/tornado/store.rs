@@ -0,0 +1,110 @@
+use anyhow::{anyhow, Result};
+use novasmt::{ContentAddrStore, InMemoryCas};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+
+/// Content addressed store of novasmt kept in an append-only file, the nodes of every tree ever
+/// saved stay in it, so any of their roots can be reopened.
+#[derive(Debug)]
+pub struct FileCas {
+    path: PathBuf,
+    node: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
+    // records inserted since the last flush
+    pending: Mutex<Vec<u8>>,
+}
+
+impl FileCas {
+    /// Load the store, an absent file is an empty store.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(err) => return Err(err.into()),
+        };
+
+        let err = || anyhow!("Tree store `{}` is broken", path.display());
+        let mut node = HashMap::new();
+        let mut rest = bytes.as_slice();
+        while !rest.is_empty() {
+            let key = read_chunk(&mut rest).ok_or_else(err)?;
+            let value = read_chunk(&mut rest).ok_or_else(err)?;
+            node.insert(key.to_vec(), value.to_vec());
+        }
+
+        Ok(Self {
+            path,
+            node: RwLock::new(node),
+            pending: Mutex::new(vec![]),
+        })
+    }
+
+    /// Append the nodes inserted since the last flush to the file.
+    pub fn flush(&self) -> Result<()> {
+        let mut pending = self.pending.lock().unwrap();
+        if !pending.is_empty() {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?
+                .write_all(&pending)?;
+            pending.clear();
+        }
+        Ok(())
+    }
+}
+
+impl ContentAddrStore for FileCas {
+    fn get<'a>(&'a self, key: &[u8]) -> Option<Cow<'a, [u8]>> {
+        self.node.read().unwrap().get(key).cloned().map(Cow::Owned)
+    }
+
+    fn insert(&self, key: &[u8], value: &[u8]) {
+        let mut node = self.node.write().unwrap();
+        if node.contains_key(key) {
+            return;
+        }
+        node.insert(key.to_vec(), value.to_vec());
+
+        let mut pending = self.pending.lock().unwrap();
+        for chunk in [key, value] {
+            pending.extend((chunk.len() as u32).to_be_bytes());
+            pending.extend(chunk);
+        }
+    }
+}
+
+/// Where the nodes of a `SparseMerkleTree` are kept.
+#[derive(Debug)]
+pub enum SmtStore {
+    Memory(InMemoryCas),
+    File(FileCas),
+}
+
+impl ContentAddrStore for SmtStore {
+    fn get<'a>(&'a self, key: &[u8]) -> Option<Cow<'a, [u8]>> {
+        match self {
+            Self::Memory(cas) => cas.get(key),
+            Self::File(cas) => cas.get(key),
+        }
+    }
+
+    fn insert(&self, key: &[u8], value: &[u8]) {
+        match self {
+            Self::Memory(cas) => cas.insert(key, value),
+            Self::File(cas) => cas.insert(key, value),
+        }
+    }
+}
+
+// a 4-byte big-endian length followed by the bytes
+fn read_chunk<'a>(rest: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = u32::from_be_bytes(rest.get(..4)?.try_into().unwrap()) as usize;
+    let chunk = rest.get(4..4 + len)?;
+    *rest = &rest[4 + len..];
+    Some(chunk)
+}
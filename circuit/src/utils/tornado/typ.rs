@@ -1,33 +1,27 @@
-use js_sys::{BigInt, Uint8Array};
-use lazy_static::lazy_static;
+use super::manifest::{Manifest, SignedManifest};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use wasm_bindgen::prelude::*;
-
-#[wasm_bindgen(module = "/output/tornado_bundle.js")]
-extern "C" {
-    pub type TornadoUtil;
-
-    #[wasm_bindgen(constructor)]
-    pub fn new() -> TornadoUtil;
-
-    #[wasm_bindgen(method)]
-    pub async fn init(this: &TornadoUtil);
-
-    #[wasm_bindgen(method)]
-    pub fn pedersen_hash(this: &TornadoUtil, data: Uint8Array) -> BigInt;
-
-    #[wasm_bindgen(method, catch)]
-    pub async fn read_file(this: &TornadoUtil, path: JsValue) -> Result<JsValue, JsValue>;
-}
+#[cfg(feature = "std")]
+use {lazy_static::lazy_static, std::collections::HashMap};
 
 // tornado note parse rule
+#[cfg(feature = "std")]
 pub const NOTE_REGEX: &str =
     r"^tornado-(?P<currency>\w+)-(?P<amount>[\d.]+)-(?P<netId>\d+)-0x(?P<note>[0-9a-fA-F]{124})$";
+// BN254 scalar field size
+pub const FIELD_SIZE: &str =
+    "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+// nullifier and secret are both 31 bytes in the note preimage
+pub const NULLIFIER_LEN: usize = 31;
+pub const PREIMAGE_LEN: usize = 62;
 // tornado event log cache file path, only cache data used for convenience
 // env::var("EVENT_LOG_DIR")?
+#[cfg(feature = "std")]
 pub const EVENT_LOG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tornado_cli/cache");
 
+#[cfg(feature = "std")]
 lazy_static! {
     pub static ref NET_NAME_MAP: HashMap<u32, &'static str> = {
         let mut map = HashMap::new();
@@ -50,11 +44,267 @@ pub type Hash = [u8; 32];
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Proof {
     pub commitment: Hash,
+    pub nullifier_hash: Hash,
     pub accuracy_tree_root: Hash,
-    pub innocence_tree_root: Hash,
+    // the deposit tree state of `accuracy_tree_root`, only its block and timestamp are part of the
+    // zkVM input with `min_delay`
+    #[serde(default)]
+    pub snapshot: Snapshot,
+    // pool of the note like `eth-0.1-5`, not part of the zkVM input
+    #[serde(default)]
+    pub pool: String,
+    // blocklist exclusion, absent in `ProofMode::Inclusion`
+    pub innocence_tree_root: Option<Hash>,
+    // allowlist inclusion, absent in `ProofMode::Exclusion`
+    pub association_tree_root: Option<Hash>,
     pub accuracy_proof_element: Vec<Hash>,
     pub accuracy_proof_index: Vec<bool>,
-    pub innocence_proof: Vec<u8>,
+    pub innocence_proof: Option<Vec<u8>>,
+    pub association_proof_element: Vec<Hash>,
+    pub association_proof_index: Vec<bool>,
+    // the on-chain withdrawal of the note, found by `nullifier_hash`, not part of the zkVM journal
+    #[serde(default)]
+    pub withdrawal: Option<WithdrawLog>,
+    // claim that the note is withdrawn at least this long after the snapshot, which is after its
+    // deposit
+    #[serde(default)]
+    pub min_delay: Option<MinDelay>,
+    // how well the deposit is hidden, not part of the zkVM input
+    #[serde(default)]
+    pub anonymity_set: AnonymitySet,
+    // exclusion from the named blocklists, next to the one of `innocence_tree_root`
+    #[serde(default)]
+    pub named_innocence: Vec<NamedInnocence>,
+    // the issuer signature of `innocence_tree_root`
+    #[serde(default)]
+    pub manifest: Option<SignedManifest>,
+}
+
+/// Non-membership proof of the commitment in a named blocklist, e.g. one kept by a verifier.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamedInnocence {
+    pub name: String,
+    pub root: Hash,
+    pub proof: Vec<u8>,
+}
+
+impl Proof {
+    /// The statement of the proof, an exclusion is from the main blocklist or a named one.
+    pub fn mode(&self) -> Option<ProofMode> {
+        match (
+            self.innocence_tree_root.is_some() || !self.named_innocence.is_empty(),
+            self.association_tree_root.is_some(),
+        ) {
+            (true, false) => Some(ProofMode::Exclusion),
+            (false, true) => Some(ProofMode::Inclusion),
+            (true, true) => Some(ProofMode::Both),
+            (false, false) => None,
+        }
+    }
+}
+
+/// The deposit tree state to prove against, the latest one in the cache by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AsOf {
+    /// the deposits up to and including this block
+    Block(u32),
+    /// the first n deposits
+    LeafCount(usize),
+}
+
+/// The deposit tree state a proof is made against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub leaf_count: usize,
+    // block of the last deposit in the tree
+    pub block_number: u32,
+    // of the block, `None` if the cache has no such timestamp
+    #[serde(default)]
+    pub timestamp: Option<u64>,
+    // the root is one of the known roots of the pool, e.g. read from the contract
+    #[serde(default)]
+    pub matched_root: Option<Hash>,
+}
+
+/// Deposits around a note in the deposit tree of its proof, and withdrawals in the blocks between
+/// its deposit and its own withdrawal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnonymitySet {
+    pub deposit_before: usize,
+    pub deposit_after: usize,
+    // `None` unless the proof is linked to the withdrawal
+    pub withdrawal_between: Option<usize>,
+}
+
+impl fmt::Display for AnonymitySet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} deposits before, {} after",
+            self.deposit_before, self.deposit_after
+        )?;
+        if let Some(count) = self.withdrawal_between {
+            write!(f, ", {count} withdrawals until its own")?;
+        }
+        Ok(())
+    }
+}
+
+/// Public output of the zkVM guest, commitments and merkle paths stay private.
+/// `accuracy_tree_root[i]` and `nullifier_hash[i]` belong to the i-th note, the nullifier hash
+/// ties the proof to the withdrawal of the note on-chain. With `min_delay` the blocks the claim is
+/// made between are public too, a verifier looks them up on-chain. With a manifest the issuer key
+/// vouches for `innocence_tree_root`, the signature itself stays private.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Journal {
+    pub accuracy_tree_root: Vec<Hash>,
+    pub innocence_tree_root: Option<Hash>,
+    pub association_tree_root: Option<Hash>,
+    pub nullifier_hash: Vec<Hash>,
+    pub min_delay: Option<MinDelay>,
+    // one for every proof with `min_delay`, none without
+    pub delay_anchor: Vec<DelayAnchor>,
+    // name and root of every named blocklist
+    pub named_innocence_root: Vec<(String, Hash)>,
+    pub issuer_key: Option<Hash>,
+    pub manifest: Option<Manifest>,
+}
+
+impl Journal {
+    /// `None` if the proofs are not made against the same blocklists, manifest and allowlist, or
+    /// do not claim the same minimum delay, or a proof lacks the withdrawal of its claim.
+    pub fn new(proof_list: &[Proof]) -> Option<Self> {
+        let first = proof_list.first()?;
+        let named_root = |proof: &Proof| {
+            proof
+                .named_innocence
+                .iter()
+                .map(|named| (named.name.clone(), named.root))
+                .collect::<Vec<_>>()
+        };
+        let named_innocence_root = named_root(first);
+        if proof_list.iter().any(|proof| {
+            proof.innocence_tree_root != first.innocence_tree_root
+                || proof.association_tree_root != first.association_tree_root
+                || proof.min_delay != first.min_delay
+                || named_root(proof) != named_innocence_root
+                || proof.manifest != first.manifest
+        }) {
+            return None;
+        }
+        let delay_anchor = match first.min_delay {
+            Some(_) => proof_list
+                .iter()
+                .map(DelayAnchor::new)
+                .collect::<Option<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+
+        Some(Self {
+            accuracy_tree_root: proof_list
+                .iter()
+                .map(|proof| proof.accuracy_tree_root)
+                .collect(),
+            innocence_tree_root: first.innocence_tree_root,
+            association_tree_root: first.association_tree_root,
+            nullifier_hash: proof_list
+                .iter()
+                .map(|proof| proof.nullifier_hash)
+                .collect(),
+            min_delay: first.min_delay,
+            delay_anchor,
+            named_innocence_root,
+            issuer_key: first.manifest.as_ref().map(|signed| signed.public_key),
+            manifest: first
+                .manifest
+                .as_ref()
+                .map(|signed| signed.manifest.clone()),
+        })
+    }
+}
+
+/// The public blocks a `min_delay` claim is made between, both can be looked up on-chain: the
+/// deposit tree root of the proof is the one of the contract as of `snapshot_block`, and the
+/// withdrawal of its nullifier hash is in `withdrawal_block`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelayAnchor {
+    pub snapshot_block: u32,
+    pub snapshot_timestamp: Option<u64>,
+    pub withdrawal_block: u32,
+    pub withdrawal_timestamp: Option<u64>,
+}
+
+impl DelayAnchor {
+    /// `None` without a withdrawal, or with a timestamp that is not a number.
+    pub fn new(proof: &Proof) -> Option<Self> {
+        let withdrawal = proof.withdrawal.as_ref()?;
+        Some(Self {
+            snapshot_block: proof.snapshot.block_number,
+            snapshot_timestamp: proof.snapshot.timestamp,
+            withdrawal_block: withdrawal.block_number,
+            withdrawal_timestamp: withdrawal
+                .timestamp
+                .as_deref()
+                .map(str::parse)
+                .transpose()
+                .ok()?,
+        })
+    }
+}
+
+/// The least time between the deposit and the withdrawal of a note. The deposit block stays
+/// private, so the time is counted from the snapshot, whose tree has every deposit up to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MinDelay {
+    Block(u32),
+    Second(u64),
+}
+
+impl MinDelay {
+    /// `false` if the withdrawal is too early, or a timestamp is missing for `Second`.
+    pub fn check(&self, anchor: &DelayAnchor) -> bool {
+        match *self {
+            Self::Block(min) => anchor
+                .withdrawal_block
+                .checked_sub(anchor.snapshot_block)
+                .is_some_and(|delay| delay >= min),
+            Self::Second(min) => anchor
+                .withdrawal_timestamp
+                .zip(anchor.snapshot_timestamp)
+                .and_then(|(withdrawal, snapshot)| withdrawal.checked_sub(snapshot))
+                .is_some_and(|delay| delay >= min),
+        }
+    }
+}
+
+impl fmt::Display for MinDelay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Block(n) => write!(f, "{n} blocks"),
+            Self::Second(n) => write!(f, "{n} seconds"),
+        }
+    }
+}
+
+/// Which statements a proof makes about the commitment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProofMode {
+    /// not in the blocklist
+    #[default]
+    Exclusion,
+    /// in the allowlist (association set)
+    Inclusion,
+    Both,
+}
+
+impl ProofMode {
+    pub fn exclusion(&self) -> bool {
+        matches!(self, Self::Exclusion | Self::Both)
+    }
+
+    pub fn inclusion(&self) -> bool {
+        matches!(self, Self::Inclusion | Self::Both)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -65,8 +315,7 @@ pub enum EventLogType {
     Withdrawal,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(untagged)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum EventLog {
     Deposit(DepositLog),
     Withdraw(WithdrawLog),
@@ -75,29 +324,25 @@ pub enum EventLog {
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DepositLog {
-    #[serde(default)]
     pub block_number: u32,
-    #[serde(default)]
     pub leaf_index: usize,
-    #[serde(default)]
     pub transaction_hash: HashStr,
-    #[serde(default)]
     pub commitment: HashStr,
-    #[serde(default)]
     pub timestamp: String,
+    // sender of the deposit transaction, not in the tornado-cli cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<Address>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WithdrawLog {
-    #[serde(default)]
     pub block_number: u32,
-    #[serde(default)]
     pub transaction_hash: HashStr,
-    #[serde(default)]
     pub nullifier_hash: HashStr,
-    #[serde(default)]
     pub to: Address,
-    #[serde(default)]
     pub fee: String,
+    // block timestamp, not in the tornado-cli cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
 }
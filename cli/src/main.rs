@@ -0,0 +1,739 @@
+use anyhow::{anyhow, bail, Result};
+use circuit::{
+    check_delay_anchor, encode_input, strip_hex_prefix, to_hash_str, try_to_hash, verify, AsOf,
+    BlockList, BlockListFormat, FsSource, MinDelay, Note, Pool, Proof, ProofMode, ProveOutput,
+    SignedManifest, SparseMerkleTree, Tornado,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use futures::executor::block_on;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// read from stdin when the input path is `-`
+const STDIN: &str = "-";
+
+/// Proof of Innocence built on Tornado Cash
+#[derive(Parser)]
+#[command(name = "poi", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+// parsed once, the size of `Prove` does not matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand)]
+enum Command {
+    /// Generate the innocence proof of tornado notes
+    Prove {
+        /// Tornado note list file, one note per line
+        #[arg(short, long, default_value = STDIN)]
+        notes: String,
+        /// Blocklist file of commitments, deposit transaction hashes or depositor addresses, csv
+        /// with `reason` and `source` columns, a json array, or one per line
+        #[arg(short, long)]
+        block_list: Option<String>,
+        /// Blocklist tree store file to prove against instead of `--block-list`, e.g.
+        /// `<tree-dir>/blocklist.smt` or one updated by `tree`
+        #[arg(long, requires = "block_list_root", conflicts_with = "block_list")]
+        block_list_tree: Option<PathBuf>,
+        /// Root of the tree in `--block-list-tree` to prove against
+        #[arg(long, requires = "block_list_tree")]
+        block_list_root: Option<String>,
+        /// Named blocklist `<name>=<file>` of a verifier in any format of `--block-list`, proven
+        /// separately, can be repeated
+        #[arg(long, value_name = "NAME=FILE")]
+        named_block_list: Vec<String>,
+        /// Signed manifest of `--block-list` or `--block-list-tree` by its issuer, made by
+        /// `manifest`
+        #[arg(long)]
+        manifest: Option<String>,
+        /// Allowed commitment list file (association set), one commitment per line
+        #[arg(short, long)]
+        allow_list: Option<String>,
+        /// Tornado event log cache directory
+        #[arg(short, long)]
+        cache: Option<PathBuf>,
+        /// Directory to keep the deposit and blocklist trees between runs, only changes are applied
+        #[arg(long)]
+        tree_dir: Option<PathBuf>,
+        /// Known root of the deposit tree, e.g. read from the contract, for every pool
+        #[arg(long)]
+        root: Vec<String>,
+        /// Known root list file, one `<currency>-<amount>-<netId> <root>` per line
+        #[arg(long)]
+        root_file: Option<String>,
+        /// Link every proof to the withdrawal of its note, notes not withdrawn fail
+        #[arg(short, long)]
+        withdrawal: bool,
+        /// Refuse to prove on a damaged deposit cache
+        #[arg(long)]
+        strict: bool,
+        /// Claim that every note is withdrawn at least this many blocks after its deposit
+        #[arg(long, conflicts_with = "min_delay_seconds")]
+        min_delay_blocks: Option<u32>,
+        /// Claim that every note is withdrawn at least this many seconds after its deposit, needs
+        /// withdrawal timestamps in the cache
+        #[arg(long)]
+        min_delay_seconds: Option<u64>,
+        /// Prove against the deposit tree as of this block
+        #[arg(long, conflicts_with = "as_of_leaf")]
+        as_of_block: Option<u32>,
+        /// Prove against the deposit tree of the first n deposits
+        #[arg(long)]
+        as_of_leaf: Option<usize>,
+        /// Proof output format
+        #[arg(short, long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+        /// Proof output file, stdout by default
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Sign the manifest of a blocklist of commitments as its issuer
+    Manifest {
+        /// Blocklist file of commitments, in any format of `prove --block-list`
+        #[arg(short, long, default_value = STDIN)]
+        block_list: String,
+        /// Issuer id
+        #[arg(short, long)]
+        issuer: String,
+        /// Version of the blocklist, the issuer should increase it on every change
+        #[arg(long)]
+        list_version: u64,
+        /// File of the hex Ed25519 secret key of the issuer
+        #[arg(short, long)]
+        key: PathBuf,
+        /// Manifest output file, stdout by default
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Verify a proof file generated by `prove`
+    Verify {
+        /// Proof file
+        #[arg(default_value = STDIN)]
+        proof: String,
+        /// Statement every proof must make
+        #[arg(short, long, value_enum)]
+        require: Option<Mode>,
+        /// Named blocklist `<name>=<root>` every proof must be excluded from, the root is the one
+        /// the verifier built from its own list, may be repeated
+        #[arg(long, value_name = "NAME=ROOT")]
+        require_block_list: Vec<String>,
+        /// Tornado event log cache directory, the blocks of a minimum delay claim are checked
+        /// against it
+        #[arg(short, long)]
+        cache: Option<PathBuf>,
+    },
+    /// Print the `--private` and `--public` arguments of zkwasm-cli for a proof file
+    Zkwasm {
+        /// Proof file
+        #[arg(default_value = STDIN)]
+        proof: String,
+        /// Tornado note list file the proofs were generated from, in the same order
+        #[arg(short, long)]
+        notes: String,
+    },
+    /// Show the pool, commitment and nullifier hash of tornado notes
+    Inspect {
+        /// Tornado note list file, one note per line
+        #[arg(default_value = STDIN)]
+        notes: String,
+    },
+    /// Check the deposit cache of the pools of tornado notes
+    Check {
+        /// Tornado note list file, one note per line
+        #[arg(default_value = STDIN)]
+        notes: String,
+        /// Tornado event log cache directory
+        #[arg(short, long)]
+        cache: Option<PathBuf>,
+    },
+    /// Insert or remove the commitments of blocklist files in a stored blocklist tree, and print
+    /// the new root
+    Tree {
+        /// Tree store file, e.g. `<tree-dir>/blocklist.smt` of `prove`, created if absent
+        #[arg(short, long)]
+        tree: PathBuf,
+        /// Root of the tree to update, the empty tree by default
+        #[arg(short, long)]
+        root: Option<String>,
+        /// Blocklist file of commitments to insert, in any format of `prove --block-list`
+        #[arg(short, long)]
+        insert: Option<String>,
+        /// Blocklist file of commitments to remove
+        #[arg(long)]
+        remove: Option<String>,
+    },
+    /// Report the deposit and withdrawal of tornado notes
+    Status {
+        /// Tornado note list file, one note per line
+        #[arg(default_value = STDIN)]
+        notes: String,
+        /// Tornado event log cache directory
+        #[arg(short, long)]
+        cache: Option<PathBuf>,
+        /// Print the report as json
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Mode {
+    /// Not in the block list or a named one
+    Exclusion,
+    /// In the allow list
+    Inclusion,
+    /// Both of them
+    Both,
+}
+
+impl From<Mode> for ProofMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Exclusion => ProofMode::Exclusion,
+            Mode::Inclusion => ProofMode::Inclusion,
+            Mode::Both => ProofMode::Both,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Compact json, the input of zkVM hosts
+    Json,
+    /// Indented json
+    Pretty,
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::Prove {
+            notes,
+            block_list,
+            block_list_tree,
+            block_list_root,
+            named_block_list,
+            manifest,
+            allow_list,
+            cache,
+            tree_dir,
+            root,
+            root_file,
+            withdrawal,
+            strict,
+            min_delay_blocks,
+            min_delay_seconds,
+            as_of_block,
+            as_of_leaf,
+            format,
+            output,
+        } => {
+            let named_block_list = named_block_list
+                .iter()
+                .map(|arg| parse_named_block_list(arg))
+                .collect::<Result<Vec<_>>>()?;
+            let input_list = [
+                Some(&notes),
+                block_list.as_ref(),
+                manifest.as_ref(),
+                allow_list.as_ref(),
+                root_file.as_ref(),
+            ];
+            if input_list
+                .into_iter()
+                .flatten()
+                .chain(named_block_list.iter().map(|(_, path)| path))
+                .filter(|path| *path == STDIN)
+                .count()
+                > 1
+            {
+                bail!("Only one of the input lists can be read from stdin");
+            }
+            // the blocklist of a named one alone is empty
+            let exclusion =
+                block_list.is_some() || block_list_tree.is_some() || !named_block_list.is_empty();
+            let mode = match (exclusion, &allow_list) {
+                (true, None) => ProofMode::Exclusion,
+                (false, Some(_)) => ProofMode::Inclusion,
+                (true, Some(_)) => ProofMode::Both,
+                (false, None) => bail!("At least one of block list and allow list is required"),
+            };
+            let block_list = match block_list {
+                Some(path) => read_block_list(&path)?,
+                None => BlockList::new(),
+            };
+            let named_block_list = named_block_list
+                .into_iter()
+                .map(|(name, path)| Ok((name, read_block_list(&path)?)))
+                .collect::<Result<Vec<_>>>()?;
+            let mut tornado =
+                Tornado::new(read_list(&notes)?, block_list.value_list(), source(cache))?
+                    .set_allow_list(read_hash_list(allow_list)?)
+                    .set_mode(mode)
+                    .set_strict(strict)
+                    .set_withdrawal(withdrawal)
+                    .set_known_root(read_root_list(root, root_file)?)?
+                    .set_named_block_list(
+                        named_block_list
+                            .iter()
+                            .map(|(name, list)| (name.clone(), list.value_list()))
+                            .collect(),
+                    )?;
+            if let (Some(path), Some(root)) = (block_list_tree, &block_list_root) {
+                tornado = tornado.set_block_list_tree(path, root)?;
+            }
+            if let Some(path) = &manifest {
+                tornado = tornado.set_manifest(serde_json::from_str(&read_input(path)?)?)?;
+            }
+            if let Some(dir) = tree_dir {
+                fs::create_dir_all(&dir)?;
+                tornado = tornado.set_tree_dir(dir);
+            }
+            let as_of = as_of_block
+                .map(AsOf::Block)
+                .or(as_of_leaf.map(AsOf::LeafCount));
+            if let Some(as_of) = as_of {
+                tornado = tornado.set_as_of(as_of);
+            }
+            let min_delay = min_delay_blocks
+                .map(MinDelay::Block)
+                .or(min_delay_seconds.map(MinDelay::Second));
+            if let Some(min_delay) = min_delay {
+                tornado = tornado.set_min_delay(min_delay);
+            }
+            let ProveOutput {
+                proof,
+                resolution,
+                cache_report,
+            } = block_on(tornado.prove_with_report())?;
+            // stdout may be the proof
+            for (pool, report) in cache_report.iter().filter(|(_, report)| !report.is_ok()) {
+                eprintln!("warning: the deposit cache of {pool} is damaged, {report}");
+            }
+            for resolution in resolution {
+                eprintln!("{resolution}");
+                let list = match &resolution.list {
+                    Some(name) => named_block_list
+                        .iter()
+                        .find(|(other, _)| other == name)
+                        .map(|(_, list)| list),
+                    None => Some(&block_list),
+                };
+                for provenance in list.map_or(&[][..], |list| list.provenance(&resolution.entry)) {
+                    eprintln!("  listed in {provenance}");
+                }
+            }
+
+            if let Some(signed) = proof.first().and_then(|proof| proof.manifest.as_ref()) {
+                eprintln!("{}", manifest_line(signed));
+            }
+
+            if !verify(proof.clone()) {
+                bail!("The proof cannot be verified, please ensure the accuracy of input.");
+            }
+            let content = match format {
+                Format::Json => serde_json::to_string(&proof)?,
+                Format::Pretty => serde_json::to_string_pretty(&proof)?,
+            };
+            match output {
+                Some(path) => fs::write(path, content)?,
+                None => println!("{content}"),
+            }
+        }
+        Command::Manifest {
+            block_list,
+            issuer,
+            list_version,
+            key,
+            output,
+        } => {
+            let key = fs::read_to_string(&key)
+                .map_err(|err| anyhow!("Failed to read `{}`: {err}", key.display()))?;
+            let key = try_to_hash(strip_hex_prefix(key.trim())).ok_or(anyhow!(
+                "The key should be the hex of a 32-byte Ed25519 secret key"
+            ))?;
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            let signed = read_block_list(&block_list)?
+                .manifest(&issuer, list_version, timestamp)?
+                .sign(&key);
+
+            eprintln!("{}", manifest_line(&signed));
+            let content = serde_json::to_string_pretty(&signed)?;
+            match output {
+                Some(path) => fs::write(path, content)?,
+                None => println!("{content}"),
+            }
+        }
+        Command::Verify {
+            proof,
+            require,
+            require_block_list,
+            cache,
+        } => {
+            let proof: Vec<Proof> = serde_json::from_str(&read_input(&proof)?)?;
+            let count = proof.len();
+            let mut named_root_list = vec![];
+            for named in proof.iter().flat_map(|proof| &proof.named_innocence) {
+                let root = to_hash_str(&named.root);
+                if !named_root_list.contains(&(named.name.clone(), root.clone())) {
+                    named_root_list.push((named.name.clone(), root));
+                }
+            }
+            let manifest_list = proof
+                .iter()
+                .filter_map(|proof| proof.manifest.clone())
+                .fold(vec![], |mut list, signed| {
+                    if !list.contains(&signed) {
+                        list.push(signed);
+                    }
+                    list
+                });
+            let anonymity_set_list = proof
+                .iter()
+                .map(|proof| proof.anonymity_set)
+                .collect::<Vec<_>>();
+            let withdrawal_list = proof
+                .iter()
+                .filter_map(|proof| {
+                    Some((
+                        proof.withdrawal.clone()?,
+                        proof.min_delay,
+                        proof.snapshot.block_number,
+                    ))
+                })
+                .collect::<Vec<_>>();
+
+            if let Some(mode) = require.map(ProofMode::from) {
+                if !proof
+                    .iter()
+                    .all(|proof| makes_statement(proof.mode(), mode))
+                {
+                    bail!("Some proofs do not make the required {mode:?} statement");
+                }
+            }
+            for arg in &require_block_list {
+                let (name, root) = parse_required_block_list(arg)?;
+                if proof.iter().any(|proof| {
+                    proof
+                        .named_innocence
+                        .iter()
+                        .all(|named| named.name != name || named.root != root)
+                }) {
+                    bail!(
+                        "Some proofs are not excluded from blocklist {name} of root 0x{}",
+                        to_hash_str(&root)
+                    );
+                }
+            }
+            let source = source(cache);
+            for (i, proof) in proof.iter().enumerate() {
+                if proof.min_delay.is_some() {
+                    block_on(check_delay_anchor(proof, &source)).map_err(|err| {
+                        anyhow!("Minimum delay of proof #{i} is not anchored: {err}")
+                    })?;
+                }
+            }
+            if !verify(proof) {
+                bail!("Proof verification failed");
+            }
+            println!("Verified {count} proof(s)");
+            for signed in &manifest_list {
+                println!("{}", manifest_line(signed));
+            }
+            for (name, root) in named_root_list {
+                println!("not in blocklist {name} of root 0x{root}");
+            }
+            for (i, anonymity_set) in anonymity_set_list.iter().enumerate() {
+                println!("anonymity set of proof #{i}: {anonymity_set}");
+            }
+            for (log, min_delay, snapshot_block) in withdrawal_list {
+                print!(
+                    "withdrawal {} in block {} to {}, fee {}",
+                    log.transaction_hash, log.block_number, log.to, log.fee
+                );
+                match min_delay {
+                    Some(min_delay) => {
+                        println!(", at least {min_delay} after block {snapshot_block} of its deposit tree")
+                    }
+                    None => println!(),
+                }
+            }
+        }
+        Command::Zkwasm { proof, notes } => {
+            if proof == STDIN && notes == STDIN {
+                bail!("Only one of the input lists can be read from stdin");
+            }
+            let proof: Vec<Proof> = serde_json::from_str(&read_input(&proof)?)?;
+            let preimage = read_list(&notes)?
+                .iter()
+                .map(|note| Note::preimage(note))
+                .collect::<Result<Vec<_>>>()?;
+            if proof.len() != preimage.len() {
+                bail!("There should be one note for every proof");
+            }
+            let (private, public) = encode_input(&proof, &preimage).ok_or(anyhow!(
+                "Proofs should be made against the same blocklists, manifest and allowlist, and claim the same minimum delay"
+            ))?;
+            let args = |input: Vec<u64>| {
+                input
+                    .iter()
+                    .map(|word| format!("{word}:i64"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+
+            println!("--private {} --public {}", args(private), args(public));
+        }
+        Command::Inspect { notes } => {
+            for note in parse_note(&notes)? {
+                println!("pool:           {}", note.pool());
+                println!("commitment:     0x{}", note.commitment());
+                println!("nullifier hash: 0x{}", note.nullifier_hash());
+                println!();
+            }
+        }
+        Command::Check { notes, cache } => {
+            let tornado = Tornado::new(read_list(&notes)?, vec![], source(cache))?;
+            let report_list = block_on(tornado.check_cache())?;
+
+            for (pool, report) in &report_list {
+                let status = if report.is_ok() { "ok" } else { "damaged" };
+                println!("{pool}: {status}, {report}");
+            }
+            if report_list.iter().any(|(_, report)| !report.is_ok()) {
+                bail!("Some deposit caches are damaged");
+            }
+        }
+        Command::Tree {
+            tree,
+            root,
+            insert,
+            remove,
+        } => {
+            if insert.as_deref() == Some(STDIN) && remove.as_deref() == Some(STDIN) {
+                bail!("Only one of the input lists can be read from stdin");
+            }
+            let root = match root {
+                Some(root) => parse_root(&root)?,
+                None => [0; 32],
+            };
+            let mut tree = SparseMerkleTree::open(tree, root)?;
+            if let Some(path) = insert {
+                for commitment in read_block_list(&path)?.commitment_list()? {
+                    tree.insert(commitment);
+                }
+            }
+            if let Some(path) = remove {
+                for commitment in read_block_list(&path)?.commitment_list()? {
+                    tree.remove(commitment);
+                }
+            }
+            tree.save()?;
+            println!("0x{}", to_hash_str(&tree.root()));
+        }
+        Command::Status { notes, cache, json } => {
+            let source = source(cache);
+            let report_list = parse_note(&notes)?
+                .iter()
+                .map(|note| block_on(note.report(&source)))
+                .collect::<Result<Vec<_>>>()?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report_list)?);
+            } else {
+                for report in report_list {
+                    println!("{report}");
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// a proof of both statements makes either one
+fn makes_statement(mode: Option<ProofMode>, required: ProofMode) -> bool {
+    mode.is_some_and(|mode| {
+        (!required.exclusion() || mode.exclusion()) && (!required.inclusion() || mode.inclusion())
+    })
+}
+
+fn manifest_line(signed: &SignedManifest) -> String {
+    let manifest = &signed.manifest;
+    format!(
+        "blocklist manifest 0x{}: {} version {}, {} entries, signed by 0x{}",
+        to_hash_str(&manifest.id()),
+        manifest.issuer,
+        manifest.version,
+        manifest.entry_count,
+        to_hash_str(&signed.public_key)
+    )
+}
+
+// short hex is zero-padded, like the roots of `--root`
+fn parse_root(root: &str) -> Result<[u8; 32]> {
+    try_to_hash(strip_hex_prefix(root)).ok_or(anyhow!("Root `{root}` should be 32 bytes of hex"))
+}
+
+// `NAME=FILE`
+fn parse_named_block_list(arg: &str) -> Result<(String, String)> {
+    match arg.split_once('=') {
+        Some((name, path)) if !name.is_empty() && !path.is_empty() => {
+            Ok((name.to_string(), path.to_string()))
+        }
+        _ => bail!("Named blocklist `{arg}` should be like `ofac=ofac.txt`"),
+    }
+}
+
+// `NAME=ROOT`
+fn parse_required_block_list(arg: &str) -> Result<(String, [u8; 32])> {
+    match arg.split_once('=') {
+        Some((name, root)) if !name.is_empty() => Ok((name.to_string(), parse_root(root)?)),
+        _ => bail!("Required blocklist `{arg}` should be like `ofac=0x...`"),
+    }
+}
+
+fn source(cache: Option<PathBuf>) -> FsSource {
+    cache.map(FsSource::new).unwrap_or_default()
+}
+
+fn parse_note(path: &str) -> Result<Vec<Note>> {
+    read_list(path)?
+        .iter()
+        .map(|note| Note::new(note))
+        .collect()
+}
+
+fn read_hash_list(path: Option<String>) -> Result<Vec<String>> {
+    Ok(match path {
+        Some(path) => read_list(&path)?
+            .into_iter()
+            .map(|hash| hash.trim_start_matches("0x").to_lowercase())
+            .collect(),
+        None => vec![],
+    })
+}
+
+// the format follows the extension, stdin is one value per line
+fn read_block_list(path: &str) -> Result<BlockList> {
+    match path {
+        STDIN => BlockList::new().parse(&read_input(path)?, BlockListFormat::Lines, "stdin"),
+        path => BlockList::new().load(path),
+    }
+}
+
+fn read_root_list(root: Vec<String>, path: Option<String>) -> Result<Vec<(Option<Pool>, String)>> {
+    let mut root_list = root
+        .into_iter()
+        .map(|root| (None, root))
+        .collect::<Vec<_>>();
+    if let Some(path) = path {
+        for line in read_list(&path)? {
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [pool, root] => root_list.push((Some(pool.parse()?), root.into())),
+                _ => bail!("Known root `{line}` should be like `eth-0.1-1 0x...`"),
+            }
+        }
+    }
+    Ok(root_list)
+}
+
+// one item per line, blank lines and `#` comments are skipped
+fn read_list(path: &str) -> Result<Vec<String>> {
+    Ok(read_input(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+fn read_input(path: &str) -> Result<String> {
+    if path == STDIN {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        Ok(content)
+    } else {
+        fs::read_to_string(path).map_err(|err| anyhow!("Failed to read `{path}`: {err}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_makes_statement() {
+        assert!(makes_statement(Some(ProofMode::Both), ProofMode::Exclusion));
+        assert!(makes_statement(Some(ProofMode::Both), ProofMode::Inclusion));
+        assert!(makes_statement(Some(ProofMode::Both), ProofMode::Both));
+        assert!(!makes_statement(
+            Some(ProofMode::Exclusion),
+            ProofMode::Both
+        ));
+        assert!(!makes_statement(
+            Some(ProofMode::Inclusion),
+            ProofMode::Exclusion
+        ));
+        assert!(!makes_statement(None, ProofMode::Exclusion));
+    }
+
+    #[test]
+    fn test_parse_root() {
+        let mut root = [0; 32];
+        root[31] = 0xab;
+        assert_eq!(parse_root("0xab").unwrap(), root);
+        assert_eq!(parse_root("0XAB").unwrap(), root);
+        assert_eq!(
+            parse_root(&format!("0x{}", "0".repeat(62) + "ab")).unwrap(),
+            root
+        );
+        assert!(parse_root(&"1".repeat(65)).is_err());
+        assert!(parse_root("0xzz").is_err());
+        assert!(parse_root("0x0xab").is_err());
+    }
+
+    #[test]
+    fn test_parse_named_block_list() {
+        assert_eq!(
+            parse_named_block_list("ofac=lists/ofac=2024.txt").unwrap(),
+            ("ofac".into(), "lists/ofac=2024.txt".into())
+        );
+        assert!(parse_named_block_list("ofac.txt").is_err());
+        assert!(parse_named_block_list("=ofac.txt").is_err());
+        assert!(parse_named_block_list("ofac=").is_err());
+
+        let (name, root) = parse_required_block_list("ofac=0xab").unwrap();
+        assert_eq!(name, "ofac");
+        assert_eq!(root[31], 0xab);
+        assert!(parse_required_block_list("ofac").is_err());
+        assert!(parse_required_block_list("ofac=0xzz").is_err());
+    }
+
+    #[test]
+    fn test_read_root_list() {
+        let path = std::env::temp_dir().join(format!("poi_root_list_{}", std::process::id()));
+        fs::write(&path, "# last roots\neth-0.1-1 0x01\n\neth-1-5  0x02\n").unwrap();
+        let root_list =
+            read_root_list(vec!["0x03".into()], Some(path.display().to_string())).unwrap();
+        assert_eq!(
+            root_list,
+            vec![
+                (None, "0x03".into()),
+                (Some("eth-0.1-1".parse().unwrap()), "0x01".into()),
+                (Some("eth-1-5".parse().unwrap()), "0x02".into()),
+            ]
+        );
+
+        fs::write(&path, "eth-0.1-1\n").unwrap();
+        assert!(read_root_list(vec![], Some(path.display().to_string())).is_err());
+        fs::write(&path, "eth-0.1 0x01\n").unwrap();
+        assert!(read_root_list(vec![], Some(path.display().to_string())).is_err());
+        fs::remove_file(&path).unwrap();
+
+        assert!(read_root_list(vec![], None).unwrap().is_empty());
+    }
+}
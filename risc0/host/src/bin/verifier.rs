@@ -0,0 +1,23 @@
+// Check a receipt saved by the prover without running it again.
+use circuit::Journal;
+use methods::METHOD_NAME_ID;
+use risc0_zkvm::serde::from_slice;
+use risc0_zkvm::Receipt;
+use std::{env, fs};
+
+fn main() {
+    let receipt_path = env::args().nth(1).expect("Usage: verifier <RECEIPT_FILE>");
+    let receipt: Receipt = serde_json::from_str(
+        &fs::read_to_string(receipt_path).expect("Receipt file should be readable"),
+    )
+    .expect("Receipt file should be saved by the prover");
+
+    // The image id pins the guest code, a receipt of any other method fails here
+    receipt
+        .verify(METHOD_NAME_ID)
+        .expect("Receipt should be generated by the proof of innocence method");
+
+    let journal: Journal = from_slice(&receipt.journal)
+        .expect("Journal should deserialize into the type committed by the guest");
+    println!("{}", serde_json::to_string_pretty(&journal).unwrap());
+}
@@ -1,21 +1,39 @@
 // TODO: Update the name of the method loaded by the prover. E.g., if the method is `multiply`, replace `METHOD_NAME_ID` with `MULTIPLY_ID` and replace `METHOD_NAME_PATH` with `MULTIPLY_PATH`
-use circuit::Proof;
+use circuit::{Journal, Note, Proof};
 use methods::{METHOD_NAME_ELF, METHOD_NAME_ID};
-use risc0_zkvm::serde::to_vec;
+use risc0_zkvm::serde::{from_slice, to_vec};
 use risc0_zkvm::Prover;
+use std::{env, fs};
 
-const PROOF: &str = include_str!("../../../circuit/output/proof.json");
+const USAGE: &str = "Usage: starter <PROOF_FILE> <NOTE_LIST_FILE> [RECEIPT_FILE]";
+const RECEIPT_FILE: &str = "receipt.json";
 
 fn main() {
-    let proof: Vec<Proof> = serde_json::from_str(PROOF).unwrap();
+    let mut args = env::args().skip(1);
+    let proof_path = args.next().expect(USAGE);
+    // the note list file, in the same order as the proof list
+    let note_path = args.next().expect(USAGE);
+    let receipt_path = args.next().unwrap_or_else(|| RECEIPT_FILE.into());
+
+    let proof: Vec<Proof> = serde_json::from_str(
+        &fs::read_to_string(proof_path).expect("Proof file should be readable"),
+    )
+    .expect("Proof file should be generated by `prove`");
+    let preimage = fs::read_to_string(note_path)
+        .expect("Note list file should be readable")
+        .split_whitespace()
+        .map(Note::preimage)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("Note list file should only contain tornado notes");
 
     // Make the prover.
     let mut prover = Prover::new(METHOD_NAME_ELF, METHOD_NAME_ID).expect(
         "Prover should be constructed from valid method source code and corresponding method ID",
     );
 
-    // TODO: Implement communication with the guest here
+    // The proof list and note preimages are private inputs, they never leave the host
     prover.add_input_u32_slice(&to_vec(&proof).unwrap());
+    prover.add_input_u32_slice(&to_vec(&preimage).unwrap());
 
     // Run prover & generate receipt
     let receipt = prover.run()
@@ -26,5 +44,12 @@ fn main() {
         "Code you have proven should successfully verify; did you specify the correct method ID?",
     );
 
-    // TODO: Implement code for transmitting or serializing the receipt for other parties to verify here
+    // Only the roots and nullifier hashes are public, hand out the receipt instead of `proof.json`
+    let journal: Journal = from_slice(&receipt.journal)
+        .expect("Journal should deserialize into the type committed by the guest");
+    fs::write(&receipt_path, serde_json::to_string(&receipt).unwrap())
+        .expect("Receipt file should be writable");
+
+    println!("{}", serde_json::to_string_pretty(&journal).unwrap());
+    println!("Receipt saved to `{receipt_path}`, check it with the `verifier` binary");
 }
@@ -1,15 +1,31 @@
 // TODO: Rename this file to change the name of this method from METHOD_NAME
 
 #![no_main]
-// #![no_std] // std support is experimental, but you can remove this to try it
+#![no_std]
 
-use circuit::{verify, Proof};
+extern crate alloc;
+
+use alloc::vec::Vec;
+use circuit::{verify, verify_preimage, Journal, Proof};
 use risc0_zkvm::guest::env;
 
 risc0_zkvm::guest::entry!(main);
 
 pub fn main() {
-    // TODO: Implement your guest code here
+    // the proof list and note preimages are private, only the roots, nullifier hashes, the
+    // minimum delay claim with the snapshot and withdrawal blocks it is made between, and the
+    // blocklist manifest with its issuer key are committed, `verify` checks the manifest signature
     let proof: Vec<Proof> = env::read();
+    let preimage: Vec<Vec<u8>> = env::read();
+    let journal = Journal::new(&proof)
+        .expect("Proofs should be made against the same blocklists, manifest and allowlist, and claim the same minimum delay");
+
+    // the commitment is recomputed from the note, so the prover must own the deposit
+    assert_eq!(proof.len(), preimage.len());
+    assert!(proof
+        .iter()
+        .zip(&preimage)
+        .all(|(proof, preimage)| verify_preimage(proof, preimage)));
     assert!(verify(proof));
+    env::commit(&journal);
 }
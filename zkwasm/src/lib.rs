@@ -1,36 +1,25 @@
+use circuit::{decode_input, verify, verify_preimage};
+use std::iter;
 use wasm_bindgen::prelude::*;
 
 extern "C" {
     fn wasm_input(_: u32) -> u64;
 }
 
+// the input layout is documented in `circuit::encode_input`, generate it by `poi zkwasm`
 #[wasm_bindgen]
 pub fn zkwasm() -> u64 {
-    let a = unsafe { wasm_input(0) };
-    let b = unsafe { wasm_input(1) };
-    // let source_list = read_addr_from_input(0);
-    // let block_list = read_addr_from_input(0);
+    let private = iter::repeat_with(|| unsafe { wasm_input(0) });
+    let public = iter::repeat_with(|| unsafe { wasm_input(1) });
+    let (proof_list, preimage_list) = decode_input(private, public).unwrap();
 
-    // circuit(source_list, block_list) as u64
-    a - b
+    // the commitment and the public nullifier hash are recomputed from the note, so the prover
+    // must own the deposit
+    assert!(proof_list
+        .iter()
+        .zip(&preimage_list)
+        .all(|(proof, preimage)| verify_preimage(proof, preimage)));
+    let res = verify(proof_list);
+    assert!(res);
+    res as u64
 }
-
-// fn read_addr_from_input(typ: u32) -> Vec<String> {
-//     let len = unsafe { wasm_input(typ as u32) };
-//     let mut result = vec![];
-//     let mut str = String::from("");
-
-//     while len > 0 {
-//         loop {
-//             let ch = unsafe { wasm_input(typ as u32) };
-//             let ch = char::from_u32(ch as u32).unwrap();
-//         }
-//     }
-//     for _ in 0..len {
-//         let ch = unsafe { wasm_input(typ as u32) };
-//         let ch = char::from_u32(ch as u32).unwrap();
-//         str.push(ch);
-//     }
-
-//     result
-// }